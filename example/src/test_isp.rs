@@ -1,57 +1,63 @@
 extern crate isp_sdk;
 use candid::Principal;
-use isp::{BurnArgs, CreateICSPResult, TopUpArgs};
-use isp_sdk::{icsp, isp};
+use isp::{BurnArgs, TopUpArgs};
+use isp_sdk::{icsp, isp, IspError};
 use std::io::Write;
 
-pub async fn get_user_icsps() {
-    let response = isp::get_user_icsps("identities/identity.pem").await;
+pub async fn get_user_icsps() -> Result<(), IspError> {
+    let response = isp::get_user_icsps("identities/identity.pem").await?;
     for i in &response {
         println!("icsp_name:{:?},icsp_canister_id:{:?}", i.0, i.1.to_text());
     }
     if response.is_empty() {
         println!("user do not have icsp\n");
     }
+    Ok(())
 }
 
-pub async fn get_sub_account() {
+pub async fn get_sub_account() -> Result<(), IspError> {
     println!(
         "SubAccount:{:?}\n",
-        isp::get_sub_account("identities/identity.pem").await
+        isp::get_sub_account("identities/identity.pem").await?
     );
+    Ok(())
 }
 
-pub async fn get_isp_admins() {
+pub async fn get_isp_admins() -> Result<(), IspError> {
     println!("isp admins:");
-    for i in &isp::get_isp_admins("identities/identity.pem").await {
+    for i in &isp::get_isp_admins("identities/identity.pem").await? {
         println!("{:?}", Principal::to_text(i));
     }
+    Ok(())
 }
 
-pub async fn get_isp_version() {
+pub async fn get_isp_version() -> Result<(), IspError> {
     println!(
         "isp version: {:?}",
-        isp::get_version("identities/identity.pem").await
+        isp::get_version("identities/identity.pem").await?
     );
+    Ok(())
 }
 
-pub async fn get_icsp_version() {
+pub async fn get_icsp_version() -> Result<(), IspError> {
     println!(
         "icsp version: {:?}",
-        icsp::get_version("identities/identity.pem", "4radi-oqaaa-aaaan-qapwa-cai").await
+        icsp::get_version("identities/identity.pem", "4radi-oqaaa-aaaan-qapwa-cai").await?
     );
+    Ok(())
 }
 
-pub async fn get_ic_file_numbers() {
+pub async fn get_ic_file_numbers() -> Result<(), IspError> {
     println!(
         "icsp 's ic file numbers: {:?}",
         icsp::get_ic_file_numbers("identities/identity.pem", "4radi-oqaaa-aaaan-qapwa-cai")
-            .await
+            .await?
             .unwrap()
     );
+    Ok(())
 }
 
-pub async fn get_field_file_infos() {
+pub async fn get_field_file_infos() -> Result<(), IspError> {
     let page_num: u64 = 10;
     let page_index: u64 = 2;
     println!(
@@ -65,7 +71,7 @@ pub async fn get_field_file_infos() {
         page_num,
         page_index,
     )
-    .await
+    .await?
     {
         index += 1;
         println!("the file_info index: {:?}", index);
@@ -78,9 +84,10 @@ pub async fn get_field_file_infos() {
         println!("total_size: {:?}", file_info.total_size);
         println!("\n");
     }
+    Ok(())
 }
 
-pub async fn create_icsp() {
+pub async fn create_icsp() -> Result<(), IspError> {
     let response = isp::create_icsp(
         "identities/identity.pem",
         "icsp-1",
@@ -88,18 +95,22 @@ pub async fn create_icsp() {
         5_000_000_000_000 as u64 - 2_000_000_000 as u64,
     )
     .await;
-    match response.0 {
-        CreateICSPResult::ok(canister_id) => {
+    match response {
+        Ok(canister_id) => {
+            println!("create icsp success: {:?}", canister_id.to_text());
+        }
+        Err(IspError::Created { canister_id, error }) => {
             println!("create icsp success: {:?}", canister_id.to_text());
-            println!("use XTC topup result: {:?}", response.1.unwrap());
+            println!("top up or init error: {}", error);
         }
-        CreateICSPResult::err(error) => {
-            println!("create icsp error: {:?}", error);
+        Err(error) => {
+            println!("create icsp error: {}", error);
         }
     }
+    Ok(())
 }
 
-pub async fn top_up_icsp() {
+pub async fn top_up_icsp() -> Result<(), IspError> {
     println!(
         "topup icsp result:{:?}\n",
         isp::top_up_icsp(
@@ -109,11 +120,12 @@ pub async fn top_up_icsp() {
                 icp_amount: 5_000_000 as u64,
             }
         )
-        .await
+        .await?
     );
+    Ok(())
 }
 
-pub async fn get_bucket_of_file() {
+pub async fn get_bucket_of_file() -> Result<(), IspError> {
     println!(
         "the file in bucekt:{:?}\n",
         icsp::get_bucket_of_file(
@@ -121,15 +133,16 @@ pub async fn get_bucket_of_file() {
             "4radi-oqaaa-aaaan-qapwa-cai",
             "c3fc027b-0917-4308-adf5-bdd460598d88",
         )
-        .await
+        .await?
         .expect("no bucket have this file")
         .to_text()
     );
+    Ok(())
 }
 
-pub async fn get_icsp_buckets() {
+pub async fn get_icsp_buckets() -> Result<(), IspError> {
     let response =
-        icsp::get_icsp_buckets("identities/identity.pem", "4radi-oqaaa-aaaan-qapwa-cai").await;
+        icsp::get_icsp_buckets("identities/identity.pem", "4radi-oqaaa-aaaan-qapwa-cai").await?;
     match response {
         Some(response) => {
             println!("dead buckets:");
@@ -151,28 +164,32 @@ pub async fn get_icsp_buckets() {
         }
         None => println!("icsp do not have buckets"),
     }
+    Ok(())
 }
 
-pub async fn get_icsp_admins() {
+pub async fn get_icsp_admins() -> Result<(), IspError> {
     println!("icsp admins:");
-    for i in &icsp::get_icsp_admins("identities/identity.pem", "4radi-oqaaa-aaaan-qapwa-cai").await
+    for i in
+        &icsp::get_icsp_admins("identities/identity.pem", "4radi-oqaaa-aaaan-qapwa-cai").await?
     {
         println!("{:?}", i.to_text());
     }
+    Ok(())
 }
 
-pub async fn top_up_bucket() {
+pub async fn top_up_bucket() -> Result<(), IspError> {
     // 0.1 T Cycles
     icsp::top_up_bucket(
         "identities/identity.pem",
         "4radi-oqaaa-aaaan-qapwa-cai",
         100_000_000_000 as u64,
     )
-    .await;
+    .await?;
     println!("complete top_up_bucket func, top up every bucket 0.1 T Cycles");
+    Ok(())
 }
 
-pub async fn store_files() {
+pub async fn store_files() -> Result<(), IspError> {
     // url format : icsp_canister_id.raw.ic0.app/'option location'/file_key
     // icsp_canister_id.raw.ic0.app/ic/file_key
     // icsp_canister_id.raw.ic0.app/ipfs/file_key
@@ -183,13 +200,14 @@ pub async fn store_files() {
         "4radi-oqaaa-aaaan-qapwa-cai",
         true,
     )
-    .await
+    .await?
     {
         println!("file_name:{:?},file_key:{:?}", i.0, i.1);
     }
+    Ok(())
 }
 
-pub async fn store_file() {
+pub async fn store_file() -> Result<(), IspError> {
     // url format : icsp_canister_id.raw.ic0.app/'option location'/file_key
     // icsp_canister_id.raw.ic0.app/ic/file_key
     // icsp_canister_id.raw.ic0.app/ipfs/file_key
@@ -200,11 +218,12 @@ pub async fn store_file() {
         "4radi-oqaaa-aaaan-qapwa-cai",
         true,
     )
-    .await;
+    .await?;
     println!("file_name:{:?},file_key:{:?}", respoonse.0, respoonse.1);
+    Ok(())
 }
 
-pub async fn store_file_by_key() {
+pub async fn store_file_by_key() -> Result<(), IspError> {
     let respoonse = icsp::store_file_by_key(
         "identities/identity.pem",
         "source/a.docx",
@@ -212,21 +231,23 @@ pub async fn store_file_by_key() {
         true,
         "1112".to_string(),
     )
-    .await;
+    .await?;
     println!("file_name:{:?},file_key:{:?}", respoonse.0, respoonse.1);
+    Ok(())
 }
 
-pub async fn delete_file() {
+pub async fn delete_file() -> Result<(), IspError> {
     let _respoonse = icsp::delete_file(
         "identities/identity.pem",
         "4radi-oqaaa-aaaan-qapwa-cai",
         "7d207a64-8621-419b-a4be-022591f4fd6e",
     )
-    .await;
+    .await?;
     println!("complete delete file func");
+    Ok(())
 }
 
-pub async fn store_str() {
+pub async fn store_str() -> Result<(), IspError> {
     // url format : icsp_canister_id.raw.ic0.app/'option location'/file_key
     // icsp_canister_id.raw.ic0.app/ic/file_key
     // icsp_canister_id.raw.ic0.app/ipfs/file_key
@@ -239,11 +260,12 @@ pub async fn store_str() {
             "test_isp_sdk_store_str",
             true,
         )
-        .await
+        .await?
     );
+    Ok(())
 }
 
-pub async fn replace_str() {
+pub async fn replace_str() -> Result<(), IspError> {
     icsp::replace_str(
         "identities/identity.pem",
         "4radi-oqaaa-aaaan-qapwa-cai",
@@ -251,17 +273,18 @@ pub async fn replace_str() {
         "test_isp_sdk_replace_str",
         true,
     )
-    .await;
+    .await?;
     println!("replace_str complete ");
+    Ok(())
 }
 
-pub async fn get_file() {
+pub async fn get_file() -> Result<(), IspError> {
     let response = icsp::get_file(
         "identities/identity.pem",
         "4radi-oqaaa-aaaan-qapwa-cai",
         "3166112af0dcc940f8e7f2199a4200cfb5e2efb40796391201b8fe9e4ff7ca84",
     )
-    .await;
+    .await?;
 
     let mut file = std::fs::File::create("output/bitcoin.pdf").expect("create failed");
     file.write_all(&response.0).expect("write failed");
@@ -270,27 +293,30 @@ pub async fn get_file() {
         "file out put at folder output/ , file_type:{:?}",
         response.1
     );
+    Ok(())
 }
 
-pub async fn add_icsp_admin() {
+pub async fn add_icsp_admin() -> Result<(), IspError> {
     icsp::add_icsp_admin(
         "identities/identity.pem",
         "4radi-oqaaa-aaaan-qapwa-cai",
         "bxgws-37y5d-tgmpr-hekbp-y3uxo-yicgs-fo7p3-ccnta-kidrz-74onh-pae",
     )
-    .await
+    .await?;
+    Ok(())
 }
 
-pub async fn delete_icsp_admin() {
+pub async fn delete_icsp_admin() -> Result<(), IspError> {
     icsp::delete_icsp_admin(
         "identities/identity.pem",
         "4radi-oqaaa-aaaan-qapwa-cai",
         "bxgws-37y5d-tgmpr-hekbp-y3uxo-yicgs-fo7p3-ccnta-kidrz-74onh-pae",
     )
-    .await
+    .await?;
+    Ok(())
 }
 
-pub async fn top_up_icsp_with_xtc() {
+pub async fn top_up_icsp_with_xtc() -> Result<(), IspError> {
     println!(
         "topup icsp with XTC result:{:?}\n",
         isp::top_up_icsp_with_xtc(
@@ -300,18 +326,20 @@ pub async fn top_up_icsp_with_xtc() {
                 amount: 1_000_000_000_000 as u64 - 2_000_000_000 as u64,
             }
         )
-        .await
+        .await?
     );
+    Ok(())
 }
 
-pub async fn get_user_sub_account_icp_balance() {
+pub async fn get_user_sub_account_icp_balance() -> Result<(), IspError> {
     println!(
         "icp balance:{:?}\n",
-        isp::get_user_sub_account_icp_balance("identities/identity.pem").await
+        isp::get_user_sub_account_icp_balance("identities/identity.pem").await?
     );
+    Ok(())
 }
 
-pub async fn transfer_out_user_sub_account_icp() {
+pub async fn transfer_out_user_sub_account_icp() -> Result<(), IspError> {
     println!(
         "transfer out icp result:{:?}\n",
         isp::transfer_out_user_sub_account_icp(
@@ -319,32 +347,35 @@ pub async fn transfer_out_user_sub_account_icp() {
             "3eee9b4671b8fde5a501288d74d21ee93042dc202104fa35051563ae35d24f2f",
             5000000 as u64
         )
-        .await
+        .await?
     );
+    Ok(())
 }
 
-pub async fn get_cycle_balance() {
+pub async fn get_cycle_balance() -> Result<(), IspError> {
     println!(
         "icsp cycle balance:{:?}\n",
-        icsp::get_cycle_balance("identities/identity.pem", "4radi-oqaaa-aaaan-qapwa-cai").await
+        icsp::get_cycle_balance("identities/identity.pem", "4radi-oqaaa-aaaan-qapwa-cai").await?
     );
+    Ok(())
 }
 
-pub async fn get_all_ic_file_key() {
+pub async fn get_all_ic_file_key() -> Result<(), IspError> {
     println!(
         "get all ic file key result: {:?}",
-        icsp::get_all_ic_file_key("identities/identity.pem", "4radi-oqaaa-aaaan-qapwa-cai").await
+        icsp::get_all_ic_file_key("identities/identity.pem", "4radi-oqaaa-aaaan-qapwa-cai").await?
     );
+    Ok(())
 }
 
-pub async fn get_file_info() {
+pub async fn get_file_info() -> Result<(), IspError> {
     println!("get file info result:");
     match icsp::get_file_info(
         "identities/identity.pem",
         "4radi-oqaaa-aaaan-qapwa-cai",
         "c3fc027b-0917-4308-adf5-bdd460598d88".to_string(),
     )
-    .await
+    .await?
     {
         None => println!("do not have this file"),
         Some(file_info) => {
@@ -357,4 +388,5 @@ pub async fn get_file_info() {
             println!("total_size: {:?}", file_info.total_size);
         }
     };
+    Ok(())
 }
@@ -1,10 +1,11 @@
 extern crate isp_sdk;
 use isp_sdk::isp_certified_log::{self, StoreLog};
+use isp_sdk::IspError;
 
-pub async fn get_buckets() {
+pub async fn get_buckets() -> Result<(), IspError> {
     let response =
         isp_certified_log::get_buckets("identities/identity.pem", "4radi-oqaaa-aaaan-qapwa-cai")
-            .await;
+            .await?;
     match response {
         Some(response) => {
             println!("old buckets:");
@@ -19,43 +20,47 @@ pub async fn get_buckets() {
         }
         None => println!("icsp do not have buckets"),
     }
+    Ok(())
 }
 
-pub async fn get_log_num() {
+pub async fn get_log_num() -> Result<(), IspError> {
     println!(
         "log num:{:?}",
         isp_certified_log::get_log_num("identities/identity.pem", "4radi-oqaaa-aaaan-qapwa-cai")
-            .await
+            .await?
     );
+    Ok(())
 }
 
-pub async fn get_logs() {
+pub async fn get_logs() -> Result<(), IspError> {
     let response = isp_certified_log::get_logs(
         "identities/identity.pem",
         "4radi-oqaaa-aaaan-qapwa-cai",
         0,
         isp_certified_log::get_log_num("identities/identity.pem", "4radi-oqaaa-aaaan-qapwa-cai")
-            .await
+            .await?
             - 1,
     )
-    .await;
+    .await?;
     match response {
         Some(response) => println!("{:?}", response),
         None => println!("no logs"),
     }
+    Ok(())
 }
 
-pub async fn get_admins() {
+pub async fn get_admins() -> Result<(), IspError> {
     println!("admins");
     for i in
         &isp_certified_log::get_admins("identities/identity.pem", "4radi-oqaaa-aaaan-qapwa-cai")
-            .await
+            .await?
     {
         println!("{:?}", i.to_text());
     }
+    Ok(())
 }
 
-pub async fn store() {
+pub async fn store() -> Result<(), IspError> {
     isp_certified_log::store(
         "identities/identity.pem",
         "4radi-oqaaa-aaaan-qapwa-cai",
@@ -63,23 +68,26 @@ pub async fn store() {
             context: "test".to_string(),
         },
     )
-    .await;
+    .await?;
+    Ok(())
 }
 
-pub async fn add_admin() {
+pub async fn add_admin() -> Result<(), IspError> {
     isp_certified_log::add_admin(
         "identities/identity.pem",
         "4radi-oqaaa-aaaan-qapwa-cai",
         "bxgws-37y5d-tgmpr-hekbp-y3uxo-yicgs-fo7p3-ccnta-kidrz-74onh-pae",
     )
-    .await;
+    .await?;
+    Ok(())
 }
 
-pub async fn delete_admin() {
+pub async fn delete_admin() -> Result<(), IspError> {
     isp_certified_log::delete_admin(
         "identities/identity.pem",
         "4radi-oqaaa-aaaan-qapwa-cai",
         "bxgws-37y5d-tgmpr-hekbp-y3uxo-yicgs-fo7p3-ccnta-kidrz-74onh-pae",
     )
-    .await;
+    .await?;
+    Ok(())
 }
@@ -1,5 +1,7 @@
 // This is an experimental feature to generate Rust binding from Candid.
 // You may want to manually adjust some of the types.
+#![allow(dead_code, non_camel_case_types, non_snake_case)]
+#![allow(clippy::upper_case_acronyms)]
 use ic_cdk::api::call::CallResult;
 use ic_cdk::export::candid::{self, CandidType, Deserialize};
 
@@ -54,17 +56,17 @@ struct StoreArgs {
     index: candid::Nat,
 }
 
-#[derive(CandidType, Deserialize)]
-struct CallbackToken {
-    key: String,
-    total_index: candid::Nat,
-    index: candid::Nat,
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CallbackToken {
+    pub key: String,
+    pub total_index: candid::Nat,
+    pub index: candid::Nat,
 }
 
-#[derive(CandidType, Deserialize)]
-struct StreamingCallbackHttpResponse {
-    token: Option<CallbackToken__1>,
-    body: Vec<u8>,
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct StreamingCallbackHttpResponse {
+    pub token: Option<CallbackToken>,
+    pub body: Vec<u8>,
 }
 
 type Bucket = candid::Service;
@@ -0,0 +1,186 @@
+use crate::IspError;
+use candid::{Decode, Encode, Nat};
+use ic_agent::Agent;
+mod bucket_did;
+pub use bucket_did::{CallbackToken, StreamingCallbackHttpResponse};
+
+/// A handle bound to one bucket canister of an icsp, created by [`IspClient::bucket`](crate::IspClient::bucket)
+///
+/// Files are normally stored and got through the icsp, which knows the bucket of every file.
+/// Calling a bucket directly is for diagnostics and for reading files from a dead bucket or
+/// when the icsp index can not be used. Every call reuses the agent of the client that created
+/// the handle.
+///
+/// # Examples
+///
+/// ``` no_run
+/// use candid::Principal;
+/// use isp_sdk::{IspClient, IspError};
+///
+/// pub async fn bucket_status(client: &IspClient) -> Result<(), IspError> {
+///     let bucket = client.bucket(Principal::from_text("5ekwd-fyaaa-aaaan-qaxlq-cai")?);
+///     println!("cycle balance:{:?}", bucket.get_cycle_balance().await?);
+///     println!("admins:{:?}", bucket.get_admins().await?);
+///     println!("buffers:{:?}", bucket.get_buffers().await?);
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone)]
+pub struct BucketHandle {
+    agent: Agent,
+    canister_id: candid::Principal,
+}
+
+impl BucketHandle {
+    pub(crate) fn new(agent: Agent, canister_id: candid::Principal) -> BucketHandle {
+        BucketHandle { agent, canister_id }
+    }
+
+    /// The bucket canister this handle is bound to
+    pub fn canister_id(&self) -> candid::Principal {
+        self.canister_id
+    }
+
+    /// Get bucket 's cycle balance
+    pub async fn get_cycle_balance(&self) -> Result<Nat, IspError> {
+        let response_blob = self
+            .agent
+            .query(&self.canister_id, "getCycleBalance")
+            .with_arg(Encode!()?)
+            .call()
+            .await?;
+        Ok(Decode!(&response_blob, Nat)?)
+    }
+
+    /// Get bucket 's admins
+    pub async fn get_admins(&self) -> Result<Vec<candid::Principal>, IspError> {
+        let response_blob = self
+            .agent
+            .query(&self.canister_id, "getAdmins")
+            .with_arg(Encode!()?)
+            .call()
+            .await?;
+        Ok(Decode!(&response_blob, Vec<candid::Principal>)?)
+    }
+
+    /// Add an admin to the bucket, return whether it was added
+    pub async fn add_admin(&self, new_admin: candid::Principal) -> Result<bool, IspError> {
+        let response_blob = self
+            .agent
+            .update(&self.canister_id, "addAdmin")
+            .with_arg(Encode!(&new_admin)?)
+            .call_and_wait()
+            .await?;
+        Ok(Decode!(&response_blob, bool)?)
+    }
+
+    /// Replace the admins of the bucket with admins, return whether they were replaced
+    pub async fn change_admin(&self, admins: Vec<candid::Principal>) -> Result<bool, IspError> {
+        let response_blob = self
+            .agent
+            .update(&self.canister_id, "changeAdmin")
+            .with_arg(Encode!(&admins)?)
+            .call_and_wait()
+            .await?;
+        Ok(Decode!(&response_blob, bool)?)
+    }
+
+    /// Get the number of chunks of the file stored in the bucket
+    pub async fn get_file_total_index(&self, file_key: &str) -> Result<Nat, IspError> {
+        let response_blob = self
+            .agent
+            .query(&self.canister_id, "getFileTotalIndex")
+            .with_arg(Encode!(&file_key)?)
+            .call()
+            .await?;
+        Ok(Decode!(&response_blob, Nat)?)
+    }
+
+    /// Get the chunk at index of the file as stored, return (data, file_type)
+    ///
+    /// Return None if the bucket does not have the chunk. The data is neither decrypted nor
+    /// decompressed and file_type keeps the metadata recorded by the sdk, use
+    /// [`IcspHandle::get_file`](crate::IcspHandle::get_file) to get the file as it was stored.
+    pub async fn get(
+        &self,
+        file_key: &str,
+        index: usize,
+    ) -> Result<Option<(Vec<u8>, String)>, IspError> {
+        let response_blob = self
+            .agent
+            .query(&self.canister_id, "get")
+            .with_arg(Encode!(&file_key, &Nat::from(index))?)
+            .call()
+            .await?;
+        Ok(Decode!(&response_blob, Option<(Vec<u8>, String)>)?)
+    }
+
+    /// Get every chunk of the file as stored, return (data, file_type), see [`BucketHandle::get`]
+    ///
+    /// Like [`IcspHandle::get_file`](crate::IcspHandle::get_file), return empty data and
+    /// file_type if the bucket has no chunk of the file.
+    ///
+    /// # Examples
+    ///
+    /// ``` no_run
+    /// use isp_sdk::bucket::BucketHandle;
+    /// use isp_sdk::IspError;
+    ///
+    /// pub async fn rescue(bucket: &BucketHandle, file_key: &str) -> Result<(), IspError> {
+    ///     let (data, file_type) = bucket.get_file(file_key).await?;
+    ///     println!("{} bytes of {}", data.len(), file_type);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn get_file(&self, file_key: &str) -> Result<(Vec<u8>, String), IspError> {
+        let total_index = self.get_file_total_index(file_key).await?;
+        let total_index = usize::try_from(&total_index.0).unwrap_or(usize::MAX);
+        let mut data = Vec::new();
+        let mut file_type = String::new();
+        for index in 0..total_index {
+            let (chunk, chunk_type) = self
+                .get(file_key, index)
+                .await?
+                .ok_or_else(|| IspError::FileNotFound(file_key.to_string()))?;
+            data.extend_from_slice(&chunk);
+            file_type = chunk_type;
+        }
+        Ok((data, file_type))
+    }
+
+    /// Get the body of the next chunk for the http streaming strategy of the bucket
+    pub async fn streaming_callback(
+        &self,
+        token: CallbackToken,
+    ) -> Result<StreamingCallbackHttpResponse, IspError> {
+        let response_blob = self
+            .agent
+            .query(&self.canister_id, "streamingCallback")
+            .with_arg(Encode!(&token)?)
+            .call()
+            .await?;
+        Ok(Decode!(&response_blob, StreamingCallbackHttpResponse)?)
+    }
+
+    /// Get the keys of the files the bucket holds chunks of that are not completely stored yet
+    pub async fn get_buffers(&self) -> Result<Vec<String>, IspError> {
+        let response_blob = self
+            .agent
+            .query(&self.canister_id, "getBuffers")
+            .with_arg(Encode!()?)
+            .call()
+            .await?;
+        Ok(Decode!(&response_blob, Vec<String>)?)
+    }
+
+    /// Delete the file or buffer of file_key, return false if the bucket does not have it
+    pub async fn delete(&self, file_key: &str) -> Result<bool, IspError> {
+        let response_blob = self
+            .agent
+            .update(&self.canister_id, "delete")
+            .with_arg(Encode!(&file_key.to_string())?)
+            .call_and_wait()
+            .await?;
+        Ok(Decode!(&response_blob, bool)?)
+    }
+}
@@ -0,0 +1,184 @@
+use crate::icsp::DataErr;
+use crate::isp::{BurnError, Error, TransferError};
+use candid::types::principal::PrincipalError;
+use ic_agent::identity::PemError;
+use ic_agent::AgentError;
+use thiserror::Error;
+
+/// Errors returned by the SDK instead of panicking
+///
+/// Transport failures are reported as [`IspError::Agent`] and can usually be retried,
+/// errors reported by the canisters themselves keep the canister's own error type.
+#[derive(Debug, Error)]
+pub enum IspError {
+    /// The pem identity could not be loaded
+    #[error("load identity error: {0}")]
+    Identity(#[from] PemError),
+
+    /// A canister id or principal text could not be parsed
+    #[error("invalid principal: {0}")]
+    InvalidPrincipal(#[from] PrincipalError),
+
+    /// The agent failed to build, reach the replica or get a reply
+    #[error("agent error: {0}")]
+    Agent(Box<AgentError>),
+
+    /// The arguments could not be encoded or the reply could not be decoded
+    #[error("candid error: {0}")]
+    Candid(#[from] candid::Error),
+
+    /// A local file or folder could not be read
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// A local path that can not be used as a file source
+    #[error("invalid file path: {0}")]
+    InvalidPath(String),
+
+    /// A local manifest file that is not valid json
+    #[error("invalid manifest: {0}")]
+    Manifest(#[from] serde_json::Error),
+
+    /// An account identifier that is not valid hex
+    #[error("invalid account identifier: {0}")]
+    InvalidAccount(#[from] hex::FromHexError),
+
+    /// No bucket of the icsp stores the file key
+    #[error("can not find bucket have file {0}")]
+    BucketNotFound(String),
+
+    /// The bucket does not have the file key
+    #[error("bucket does not have file {0}")]
+    FileNotFound(String),
+
+    /// Some chunks of the file could not be stored, the other chunks were stored
+    #[error("store {} chunks of file {file_key} error{}", .failures.len(), first_failure(.failures))]
+    Upload {
+        file_key: String,
+        failures: Vec<ChunkFailure>,
+    },
+
+    /// The icsp did not receive every chunk of the file
+    #[error("file {file_key} received {received} of {total_index} chunks")]
+    Incomplete {
+        file_key: String,
+        received: candid::Nat,
+        total_index: candid::Nat,
+    },
+
+    /// The file got from the icsp does not have the digest recorded when it was stored
+    #[error("file {file_key} has sha256 {actual}, expected {expected}")]
+    Integrity {
+        file_key: String,
+        expected: String,
+        actual: String,
+    },
+
+    /// Replacing the file failed after the old file was deleted and the old file could not be stored again
+    #[error("replace file {file_key} error: {error}, restore old file error: {restore_error}")]
+    Restore {
+        file_key: String,
+        error: Box<IspError>,
+        restore_error: Box<IspError>,
+    },
+
+    /// The file is encrypted and the handle has no encryption key
+    #[error("file {0} is encrypted, no encryption key is set")]
+    EncryptionKeyMissing(String),
+
+    /// The file is encrypted with another key than the key of the handle
+    #[error("file {0} is encrypted with another key")]
+    WrongEncryptionKey(String),
+
+    /// A chunk of an encrypted file was modified or is not a chunk of this file
+    #[error("chunk {index} of file {file_key} can not be decrypted")]
+    Decryption { file_key: String, index: usize },
+
+    /// The file is stored compressed, its stored bytes do not map to byte ranges of the file
+    #[error("file {0} is compressed and can not be read by byte range")]
+    Compressed(String),
+
+    /// A compressed file can not be http open, the gateway would serve its compressed bytes
+    #[error("a compressed file can not be stored http open")]
+    CompressedHttpOpen,
+
+    /// Error reported by the isp canister
+    #[error("isp error: {0:?}")]
+    Isp(Error),
+
+    /// Error reported by the icsp canister
+    #[error("icsp error: {0:?}")]
+    Data(DataErr),
+
+    /// The icsp canister_id was created but topping it up or initializing it failed
+    ///
+    /// The icp is spent, retry the top up and `init` on canister_id instead of creating another icsp.
+    #[error("icsp {canister_id} created, then error: {error}")]
+    Created {
+        canister_id: candid::Principal,
+        error: Box<IspError>,
+    },
+
+    /// Error reported by the [XTC](https://github.com/Psychedelic/dank/tree/main/xtc) canister when burning to canister_id
+    #[error("burn XTC to {canister_id} error: {error:?}")]
+    Burn {
+        canister_id: candid::Principal,
+        error: BurnError,
+    },
+
+    /// Error reported by the ledger when transferring icp out of the isp subAccount
+    #[error("transfer icp error: {0:?}")]
+    Transfer(TransferError),
+}
+
+/// A chunk that could not be stored, see [`IspError::Upload`]
+#[derive(Debug)]
+pub struct ChunkFailure {
+    /// Index of the chunk in the file
+    pub index: usize,
+    /// Why storing the chunk failed
+    pub error: IspError,
+}
+
+// The first failure of an upload error message, failures may be built empty by callers
+fn first_failure(failures: &[ChunkFailure]) -> String {
+    failures
+        .first()
+        .map(|failure| format!(", first error: {}", failure.error))
+        .unwrap_or_default()
+}
+
+impl From<AgentError> for IspError {
+    fn from(error: AgentError) -> Self {
+        IspError::Agent(Box::new(error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn upload_error_without_failures_formats() {
+        let error = IspError::Upload {
+            file_key: "key".to_string(),
+            failures: Vec::new(),
+        };
+        assert_eq!(error.to_string(), "store 0 chunks of file key error");
+    }
+
+    #[test]
+    fn upload_error_shows_first_failure() {
+        let error = IspError::Upload {
+            file_key: "key".to_string(),
+            failures: vec![ChunkFailure {
+                index: 3,
+                error: IspError::FileNotFound("key".to_string()),
+            }],
+        };
+        assert_eq!(
+            error.to_string(),
+            "store 1 chunks of file key error, first error: bucket does not have file key"
+        );
+    }
+}
@@ -0,0 +1,199 @@
+use std::io::{self, Seek, Write};
+use tokio::io::{AsyncRead, AsyncSeekExt};
+
+/// Compression applied to a file before it is chunked and stored
+///
+/// The compression is recorded with the file, `get_file` decompresses it and returns the
+/// original file_type.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    /// gzip at the default level, fast and supported everywhere
+    Gzip,
+    /// zstd at the default level, compresses better and faster than gzip
+    Zstd,
+}
+
+impl Compression {
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+        }
+    }
+
+    pub(crate) fn from_name(name: &str) -> Option<Compression> {
+        match name {
+            "gzip" => Some(Compression::Gzip),
+            "zstd" => Some(Compression::Zstd),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn compress(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+        self.compress_to(&mut &data[..], Vec::new())
+    }
+
+    // Compress file into an anonymous temporary file, return it rewound with its size
+    pub(crate) async fn compress_file(
+        self,
+        mut file: std::fs::File,
+    ) -> io::Result<(tokio::fs::File, u64)> {
+        tokio::task::spawn_blocking(move || {
+            let mut compressed = self.compress_to(&mut file, tempfile::tempfile()?)?;
+            let size = compressed.stream_position()?;
+            compressed.rewind()?;
+            Ok((tokio::fs::File::from_std(compressed), size))
+        })
+        .await
+        .map_err(io::Error::other)?
+    }
+
+    // Compress everything read from reader like compress_file, return (file, original size, size)
+    pub(crate) async fn compress_reader<R: AsyncRead + Unpin>(
+        self,
+        mut reader: R,
+    ) -> io::Result<(tokio::fs::File, u64, u64)> {
+        let mut copy = tokio::fs::File::from_std(tempfile::tempfile()?);
+        let original_size = tokio::io::copy(&mut reader, &mut copy).await?;
+        copy.rewind().await?;
+        let (compressed, size) = self.compress_file(copy.into_std().await).await?;
+        Ok((compressed, original_size, size))
+    }
+
+    fn compress_to<R: io::Read, W: Write>(&self, reader: &mut R, writer: W) -> io::Result<W> {
+        match self {
+            Compression::Gzip => {
+                let mut encoder = flate2::write::GzEncoder::new(writer, Default::default());
+                io::copy(reader, &mut encoder)?;
+                encoder.finish()
+            }
+            Compression::Zstd => {
+                let mut encoder = zstd::stream::write::Encoder::new(writer, 0)?;
+                io::copy(reader, &mut encoder)?;
+                encoder.finish()
+            }
+        }
+    }
+}
+
+// Decodes the stored bytes of a file back to the original bytes, chunk by chunk
+pub(crate) enum ChunkDecoder {
+    Identity,
+    Gzip(flate2::write::GzDecoder<Vec<u8>>),
+    Zstd(zstd::stream::write::Decoder<'static, Vec<u8>>),
+}
+
+impl ChunkDecoder {
+    pub(crate) fn new(compression: Option<Compression>) -> io::Result<ChunkDecoder> {
+        Ok(match compression {
+            None => ChunkDecoder::Identity,
+            Some(Compression::Gzip) => {
+                ChunkDecoder::Gzip(flate2::write::GzDecoder::new(Vec::new()))
+            }
+            Some(Compression::Zstd) => {
+                ChunkDecoder::Zstd(zstd::stream::write::Decoder::new(Vec::new())?)
+            }
+        })
+    }
+
+    // Decode the next chunk, return the original bytes decoded so far
+    pub(crate) fn decode(&mut self, data: Vec<u8>) -> io::Result<Vec<u8>> {
+        match self {
+            ChunkDecoder::Identity => Ok(data),
+            ChunkDecoder::Gzip(decoder) => {
+                decoder.write_all(&data)?;
+                Ok(std::mem::take(decoder.get_mut()))
+            }
+            ChunkDecoder::Zstd(decoder) => {
+                decoder.write_all(&data)?;
+                Ok(std::mem::take(decoder.get_mut()))
+            }
+        }
+    }
+
+    // Return the rest of the original bytes after the last chunk
+    pub(crate) fn finish(self) -> io::Result<Vec<u8>> {
+        match self {
+            ChunkDecoder::Identity => Ok(Vec::new()),
+            ChunkDecoder::Gzip(decoder) => decoder.finish(),
+            ChunkDecoder::Zstd(mut decoder) => {
+                decoder.flush()?;
+                Ok(decoder.into_inner())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Compressible bytes that are not a single repeated pattern
+    fn sample(len: usize) -> Vec<u8> {
+        (0..len).map(|i| ((i * i) % 251 % 16) as u8).collect()
+    }
+
+    // Decode the stored bytes fed in chunks of chunk_size
+    fn decode_in_chunks(
+        compression: Option<Compression>,
+        stored: &[u8],
+        chunk_size: usize,
+    ) -> Vec<u8> {
+        let mut decoder = ChunkDecoder::new(compression).unwrap();
+        let mut data = Vec::new();
+        for chunk in stored.chunks(chunk_size) {
+            data.extend(decoder.decode(chunk.to_vec()).unwrap());
+        }
+        data.extend(decoder.finish().unwrap());
+        data
+    }
+
+    #[test]
+    fn names_round_trip() {
+        for compression in [Compression::Gzip, Compression::Zstd] {
+            assert_eq!(
+                Compression::from_name(compression.name()),
+                Some(compression)
+            );
+        }
+        assert_eq!(Compression::from_name("br"), None);
+    }
+
+    #[test]
+    fn decodes_across_chunk_boundaries() {
+        let data = sample(300_000);
+        for compression in [Compression::Gzip, Compression::Zstd] {
+            let stored = compression.compress(&data).unwrap();
+            assert!(stored.len() < data.len());
+            for chunk_size in [1, 7, 4096, stored.len()] {
+                let decoded = decode_in_chunks(Some(compression), &stored, chunk_size);
+                assert_eq!(
+                    decoded, data,
+                    "{:?} in chunks of {}",
+                    compression, chunk_size
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn decodes_empty_files() {
+        for compression in [Compression::Gzip, Compression::Zstd] {
+            let stored = compression.compress(&[]).unwrap();
+            assert!(decode_in_chunks(Some(compression), &stored, 3).is_empty());
+        }
+    }
+
+    #[test]
+    fn identity_passes_chunks_through() {
+        let data = sample(10_000);
+        assert_eq!(decode_in_chunks(None, &data, 999), data);
+    }
+
+    #[test]
+    fn rejects_bytes_that_are_not_compressed() {
+        let data = sample(10_000);
+        let mut decoder = ChunkDecoder::new(Some(Compression::Zstd)).unwrap();
+        assert!(decoder.decode(data).is_err());
+    }
+}
@@ -0,0 +1,182 @@
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use std::fmt;
+
+const NONCE_SIZE: usize = 12;
+const TAG_SIZE: usize = 16;
+
+// Bytes an encrypted chunk is longer than the chunk, the nonce and the authentication tag
+pub(crate) const CHUNK_OVERHEAD: u64 = (NONCE_SIZE + TAG_SIZE) as u64;
+
+/// A 256-bit AES-GCM key encrypting files on the client before they are stored
+///
+/// Every chunk is encrypted and authenticated on its own, the key never leaves the client.
+/// Only a fingerprint of the key is stored with the file to detect a wrong key,
+/// a file can not be read back without the key. Instead of the sha256 of the content, which
+/// would let anyone confirm a guessed content, an encrypted file records a keyed digest of it.
+/// The keyed digest is also the file_key of a content addressed encrypted file.
+#[derive(Clone)]
+pub struct EncryptionKey {
+    key: [u8; 32],
+    fingerprint: String,
+}
+
+impl EncryptionKey {
+    /// Use the 32 bytes as key
+    pub fn from_bytes(key: [u8; 32]) -> EncryptionKey {
+        let mut hasher = Sha256::new();
+        hasher.update(b"isp-sdk encryption key");
+        hasher.update(key);
+        let fingerprint = hex::encode(&hasher.finalize()[..8]);
+        EncryptionKey { key, fingerprint }
+    }
+
+    /// Generate a random key
+    pub fn generate() -> EncryptionKey {
+        EncryptionKey::from_bytes(Aes256Gcm::generate_key(&mut OsRng).into())
+    }
+
+    /// The bytes of the key
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.key
+    }
+
+    pub(crate) fn fingerprint(&self) -> &str {
+        &self.fingerprint
+    }
+
+    // Encrypt the chunk at index of the file, return nonce || ciphertext
+    pub(crate) fn encrypt_chunk(&self, file_key: &str, index: usize, chunk: &[u8]) -> Vec<u8> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let aad = chunk_aad(file_key, index);
+        let ciphertext = self
+            .cipher()
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: chunk,
+                    aad: &aad,
+                },
+            )
+            .expect("aes-gcm encryption of a chunk can not fail");
+        let mut data = nonce.to_vec();
+        data.extend(ciphertext);
+        data
+    }
+
+    // Decrypt nonce || ciphertext, return None if it was not encrypted by this key for this chunk
+    pub(crate) fn decrypt_chunk(
+        &self,
+        file_key: &str,
+        index: usize,
+        data: &[u8],
+    ) -> Option<Vec<u8>> {
+        if data.len() < NONCE_SIZE {
+            return None;
+        }
+        let (nonce, ciphertext) = data.split_at(NONCE_SIZE);
+        let aad = chunk_aad(file_key, index);
+        self.cipher()
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: &aad,
+                },
+            )
+            .ok()
+    }
+
+    // Keyed digest of the content of a file with the hex sha256, recorded instead of the sha256
+    pub(crate) fn digest_mac(&self, sha256: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(b"isp-sdk digest key");
+        hasher.update(self.key);
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&hasher.finalize())
+            .expect("hmac accepts keys of any size");
+        mac.update(sha256.as_bytes());
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    fn cipher(&self) -> Aes256Gcm {
+        Aes256Gcm::new(&self.key.into())
+    }
+}
+
+impl fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EncryptionKey")
+            .field("fingerprint", &self.fingerprint)
+            .finish_non_exhaustive()
+    }
+}
+
+// Bind a chunk to its file and position, chunks can not be swapped between files or indices
+fn chunk_aad(file_key: &str, index: usize) -> Vec<u8> {
+    let mut aad = file_key.as_bytes().to_vec();
+    aad.extend((index as u64).to_be_bytes());
+    aad
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunk_round_trips() {
+        let key = EncryptionKey::generate();
+        let encrypted = key.encrypt_chunk("file", 2, b"chunk");
+        assert_eq!(encrypted.len() as u64, 5 + CHUNK_OVERHEAD);
+        assert_eq!(
+            key.decrypt_chunk("file", 2, &encrypted),
+            Some(b"chunk".to_vec())
+        );
+        assert_eq!(
+            key.decrypt_chunk("file", 2, &key.encrypt_chunk("file", 2, b"")),
+            Some(Vec::new())
+        );
+    }
+
+    #[test]
+    fn wrong_key_can_not_decrypt() {
+        let key = EncryptionKey::from_bytes([1; 32]);
+        let other = EncryptionKey::from_bytes([2; 32]);
+        let encrypted = key.encrypt_chunk("file", 0, b"chunk");
+        assert_eq!(other.decrypt_chunk("file", 0, &encrypted), None);
+        assert_ne!(key.fingerprint(), other.fingerprint());
+    }
+
+    #[test]
+    fn chunk_is_bound_to_its_file_and_index() {
+        let key = EncryptionKey::generate();
+        let encrypted = key.encrypt_chunk("file", 1, b"chunk");
+        assert_eq!(key.decrypt_chunk("file", 0, &encrypted), None);
+        assert_eq!(key.decrypt_chunk("other", 1, &encrypted), None);
+    }
+
+    #[test]
+    fn tampered_or_short_chunk_can_not_decrypt() {
+        let key = EncryptionKey::generate();
+        let mut encrypted = key.encrypt_chunk("file", 0, b"chunk");
+        *encrypted.last_mut().unwrap() ^= 1;
+        assert_eq!(key.decrypt_chunk("file", 0, &encrypted), None);
+        assert_eq!(
+            key.decrypt_chunk("file", 0, &encrypted[..NONCE_SIZE - 1]),
+            None
+        );
+    }
+
+    #[test]
+    fn digest_mac_depends_on_the_key() {
+        let key = EncryptionKey::from_bytes([1; 32]);
+        let same = EncryptionKey::from_bytes([1; 32]);
+        let other = EncryptionKey::from_bytes([2; 32]);
+        let sha256 = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+        assert_eq!(key.digest_mac(sha256), same.digest_mac(sha256));
+        assert_ne!(key.digest_mac(sha256), other.digest_mac(sha256));
+        assert_ne!(key.digest_mac(sha256), sha256);
+        assert_eq!(key.digest_mac(sha256).len(), 64);
+    }
+}
@@ -0,0 +1,152 @@
+use crate::IspError;
+use candid::{Nat, Principal};
+use std::time::SystemTime;
+
+/// Leftovers of failed uploads found by [`IcspHandle::scan_garbage`](crate::IcspHandle::scan_garbage)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GarbageScan {
+    /// When the scan was made
+    pub scanned_at: SystemTime,
+    /// Buffers of live buckets holding chunks of files that are not completely stored
+    pub buffers: Vec<StaleBuffer>,
+    /// Files the icsp did not receive every chunk of
+    pub incomplete_files: Vec<IncompleteFile>,
+}
+
+impl GarbageScan {
+    pub(crate) fn new() -> GarbageScan {
+        GarbageScan {
+            scanned_at: SystemTime::now(),
+            buffers: Vec::new(),
+            incomplete_files: Vec::new(),
+        }
+    }
+
+    /// Whether nothing was found
+    pub fn is_empty(&self) -> bool {
+        self.buffers.is_empty() && self.incomplete_files.is_empty()
+    }
+
+    // What this scan found that earlier found too and made no progress since
+    pub(crate) fn stalled_since(&self, earlier: &GarbageScan) -> GarbageScan {
+        let incomplete_files: Vec<IncompleteFile> = self
+            .incomplete_files
+            .iter()
+            .filter(|file| earlier.incomplete_files.contains(file))
+            .cloned()
+            .collect();
+        // a buffer whose file received chunks since is still in use
+        let progressed = |file_key: &str| {
+            self.incomplete_files
+                .iter()
+                .any(|file| file.file_key == file_key && !incomplete_files.contains(file))
+        };
+        let buffers = self
+            .buffers
+            .iter()
+            .filter(|buffer| earlier.buffers.contains(buffer) && !progressed(&buffer.file_key))
+            .cloned()
+            .collect();
+        GarbageScan {
+            scanned_at: self.scanned_at,
+            buffers,
+            incomplete_files,
+        }
+    }
+}
+
+/// What [`IcspHandle::collect_garbage`](crate::IcspHandle::collect_garbage) did
+#[derive(Debug)]
+pub struct GarbageCollection {
+    /// What was deleted
+    pub deleted: GarbageScan,
+    /// Files and buffers that could not be deleted, the others were deleted
+    pub failures: Vec<DeleteFailure>,
+}
+
+/// A file or buffer that could not be deleted, see [`GarbageCollection`]
+#[derive(Debug)]
+pub struct DeleteFailure {
+    /// Key of the file or buffer
+    pub file_key: String,
+    /// Why deleting it failed
+    pub error: IspError,
+}
+
+/// A bucket buffer found by [`IcspHandle::scan_garbage`](crate::IcspHandle::scan_garbage)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StaleBuffer {
+    /// The bucket holding the buffer
+    pub bucket_canister_id: Principal,
+    /// Key of the file being stored in the buffer
+    pub file_key: String,
+}
+
+/// An incomplete file found by [`IcspHandle::scan_garbage`](crate::IcspHandle::scan_garbage)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IncompleteFile {
+    /// Key of the file
+    pub file_key: String,
+    /// The bucket the file is stored in
+    pub bucket_canister_id: Principal,
+    /// Chunks the icsp received
+    pub received: Nat,
+    /// Number of chunks of the file
+    pub total_index: Nat,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn incomplete(file_key: &str, received: usize) -> IncompleteFile {
+        IncompleteFile {
+            file_key: file_key.to_string(),
+            bucket_canister_id: Principal::anonymous(),
+            received: Nat::from(received),
+            total_index: Nat::from(10),
+        }
+    }
+
+    fn buffer(file_key: &str) -> StaleBuffer {
+        StaleBuffer {
+            bucket_canister_id: Principal::anonymous(),
+            file_key: file_key.to_string(),
+        }
+    }
+
+    fn scan(incomplete_files: Vec<IncompleteFile>, buffers: Vec<StaleBuffer>) -> GarbageScan {
+        GarbageScan {
+            incomplete_files,
+            buffers,
+            ..GarbageScan::new()
+        }
+    }
+
+    #[test]
+    fn keeps_what_made_no_progress() {
+        let earlier = scan(vec![incomplete("a", 2)], vec![buffer("a"), buffer("b")]);
+        let later = scan(vec![incomplete("a", 2)], vec![buffer("a"), buffer("b")]);
+        let stalled = later.stalled_since(&earlier);
+        assert_eq!(stalled.incomplete_files, vec![incomplete("a", 2)]);
+        assert_eq!(stalled.buffers, vec![buffer("a"), buffer("b")]);
+        assert_eq!(stalled.scanned_at, later.scanned_at);
+    }
+
+    #[test]
+    fn skips_files_that_received_chunks() {
+        let earlier = scan(vec![incomplete("a", 2)], vec![buffer("a"), buffer("b")]);
+        let later = scan(vec![incomplete("a", 3)], vec![buffer("a"), buffer("b")]);
+        let stalled = later.stalled_since(&earlier);
+        assert!(stalled.incomplete_files.is_empty());
+        assert_eq!(stalled.buffers, vec![buffer("b")]);
+    }
+
+    #[test]
+    fn skips_what_earlier_did_not_find() {
+        let earlier = scan(vec![incomplete("a", 2)], vec![buffer("a")]);
+        let later = scan(vec![incomplete("b", 0)], vec![buffer("b")]);
+        assert!(later.stalled_since(&earlier).is_empty());
+        assert!(later.stalled_since(&GarbageScan::new()).is_empty());
+    }
+}
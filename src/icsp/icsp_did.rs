@@ -1,3 +1,5 @@
+#![allow(dead_code, non_camel_case_types, non_snake_case)]
+#![allow(clippy::upper_case_acronyms)]
 use candid::{Nat, Principal};
 use ic_cdk::api::call::CallResult;
 use ic_cdk::export::candid::{CandidType, Deserialize};
@@ -14,6 +16,18 @@ pub struct Buckets {
     pub dead_buckets: Vec<BucketStatusExt>,
 }
 
+#[derive(CandidType, Deserialize, Debug)]
+pub enum DataErr {
+    BucketEnough,
+    CycleNotEnough,
+}
+
+#[derive(CandidType, Deserialize, Debug)]
+pub enum InitResult {
+    ok(BucketStatusExt),
+    err(DataErr),
+}
+
 #[derive(CandidType, Deserialize, Debug)]
 pub struct FileBufExt {
     pub bucket_id: Principal,
@@ -36,16 +50,16 @@ pub struct StoreArgs {
     pub index: Nat,
 }
 
-#[derive(CandidType, Deserialize, Debug)]
-struct OtherFile {
+#[derive(CandidType, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct OtherFile {
     pub file_location: FileLocation,
     pub file_key: String,
     pub file_url: String,
     pub file_type: String,
 }
 
-#[derive(CandidType, Deserialize, Debug)]
-enum FileLocation {
+#[derive(CandidType, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileLocation {
     IPFS,
     Arweave,
 }
@@ -82,7 +96,7 @@ impl SERVICE {
         ic_cdk::call(self.0, "delete", (arg0,)).await
     }
 
-    pub async fn init(&self) -> CallResult<(BucketStatusExt,)> {
+    pub async fn init(&self) -> CallResult<(InitResult,)> {
         ic_cdk::call(self.0, "init", ()).await
     }
 
@@ -0,0 +1,174 @@
+use super::BatchProgress;
+use crate::{IspError, Network};
+use candid::Principal;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Files stored from a directory, keyed by their path relative to the directory
+///
+/// Paths use `/` as separator on every platform, e.g. `assets/logo.png`.
+pub type Manifest = BTreeMap<String, ManifestEntry>;
+
+/// A file stored from a directory, see [`Manifest`]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// Key of the stored file
+    pub file_key: String,
+    /// MIME type of the file, without the metadata recorded by the sdk
+    pub file_type: String,
+    /// Size of the local file in bytes
+    pub size: u64,
+    /// Hex sha256 of the local file
+    pub sha256: String,
+}
+
+/// A directory published by [`IcspHandle::deploy_dir`](crate::IcspHandle::deploy_dir)
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Deployment {
+    /// The icsp the directory is published to
+    pub canister_id: Principal,
+    /// Every file of the directory, the key of a file is its relative path
+    pub manifest: Manifest,
+    /// Relative paths of the files uploaded by the deployment
+    pub uploaded: Vec<String>,
+    /// Relative paths of the files the icsp already had with the same content and type
+    pub unchanged: Vec<String>,
+    /// Staging copies of replaced files that could not be deleted
+    pub staging_keys: Vec<String>,
+}
+
+impl Deployment {
+    pub(crate) fn new(canister_id: Principal) -> Deployment {
+        Deployment {
+            canister_id,
+            manifest: Manifest::new(),
+            uploaded: Vec::new(),
+            unchanged: Vec::new(),
+            staging_keys: Vec::new(),
+        }
+    }
+
+    /// Url of the file at relative_path on the network the directory was deployed to
+    ///
+    /// On mainnet e.g. `https://icsp_canister_id.raw.ic0.app/ic/assets/app.js`, on any other
+    /// network the canister is passed as query, e.g.
+    /// `http://127.0.0.1:4943/ic/assets/app.js?canisterId=icsp_canister_id`.
+    pub fn url(&self, network: &Network, relative_path: &str) -> Option<String> {
+        let entry = self.manifest.get(relative_path)?;
+        let canister_id = self.canister_id.to_text();
+        Some(match network {
+            Network::Mainnet => {
+                format!("https://{}.raw.ic0.app/ic/{}", canister_id, entry.file_key)
+            }
+            network => format!(
+                "{}/ic/{}?canisterId={}",
+                network.url().trim_end_matches('/'),
+                entry.file_key,
+                canister_id
+            ),
+        })
+    }
+}
+
+// The manifest written at path as json, empty if there is no file at path
+pub(crate) fn read_manifest(path: &Path) -> Result<Manifest, IspError> {
+    match std::fs::read(path) {
+        Ok(json) => Ok(serde_json::from_slice(&json)?),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Manifest::new()),
+        Err(error) => Err(error.into()),
+    }
+}
+
+pub(crate) fn write_manifest(path: &Path, manifest: &Manifest) -> Result<(), IspError> {
+    std::fs::write(path, serde_json::to_vec_pretty(manifest)?)?;
+    Ok(())
+}
+
+// Every file under root and its subdirectories in path order as (relative path, path),
+// with the batch totals of the files
+pub(crate) fn walk_dir(root: &Path) -> Result<(Vec<(String, PathBuf)>, BatchProgress), IspError> {
+    let mut files = Vec::new();
+    let mut batch = BatchProgress::default();
+    for entry in WalkDir::new(root).sort_by_file_name() {
+        let entry = entry.map_err(io::Error::from)?;
+        if entry.file_type().is_file() {
+            batch.total_bytes += entry.metadata().map_err(io::Error::from)?.len();
+            files.push((relative_path(root, entry.path())?, entry.into_path()));
+        }
+    }
+    batch.total_files = files.len();
+    Ok((files, batch))
+}
+
+// Path of file_path relative to root, joined with `/`
+fn relative_path(root: &Path, file_path: &Path) -> Result<String, IspError> {
+    let invalid = || IspError::InvalidPath(file_path.display().to_string());
+    let relative = file_path.strip_prefix(root).map_err(|_| invalid())?;
+    let mut parts = Vec::new();
+    for component in relative.components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_str().ok_or_else(invalid)?),
+            _ => return Err(invalid()),
+        }
+    }
+    if parts.is_empty() {
+        return Err(invalid());
+    }
+    Ok(parts.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn url_depends_on_the_network() {
+        let canister_id = Principal::from_text("4radi-oqaaa-aaaan-qapwa-cai").unwrap();
+        let mut deployment = Deployment::new(canister_id);
+        deployment.manifest.insert(
+            "assets/app.js".to_string(),
+            ManifestEntry {
+                file_key: "assets/app.js".to_string(),
+                file_type: "text/javascript".to_string(),
+                size: 1,
+                sha256: String::new(),
+            },
+        );
+        assert_eq!(
+            deployment.url(&Network::Mainnet, "assets/app.js").unwrap(),
+            "https://4radi-oqaaa-aaaan-qapwa-cai.raw.ic0.app/ic/assets/app.js"
+        );
+        assert_eq!(
+            deployment.url(&Network::Local, "assets/app.js").unwrap(),
+            "http://127.0.0.1:4943/ic/assets/app.js?canisterId=4radi-oqaaa-aaaan-qapwa-cai"
+        );
+        let custom = Network::Custom("https://replica.example.com/".to_string());
+        assert_eq!(
+            deployment.url(&custom, "assets/app.js").unwrap(),
+            "https://replica.example.com/ic/assets/app.js?canisterId=4radi-oqaaa-aaaan-qapwa-cai"
+        );
+        assert_eq!(deployment.url(&Network::Mainnet, "index.html"), None);
+    }
+
+    #[test]
+    fn relative_path_joins_with_slash() {
+        let root = Path::new("dist");
+        let file_path = root.join("assets").join("app.js");
+        assert_eq!(relative_path(root, &file_path).unwrap(), "assets/app.js");
+        assert_eq!(
+            relative_path(root, &root.join("index.html")).unwrap(),
+            "index.html"
+        );
+    }
+
+    #[test]
+    fn relative_path_rejects_paths_outside_root() {
+        let root = Path::new("dist");
+        assert!(relative_path(root, Path::new("other/index.html")).is_err());
+        assert!(relative_path(root, root).is_err());
+        assert!(relative_path(root, &root.join("..").join("secret")).is_err());
+    }
+}
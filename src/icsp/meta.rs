@@ -0,0 +1,152 @@
+use super::compression::Compression;
+
+// The sdk records metadata of a file as `isp-` parameters of its file_type,
+// e.g. `application/pdf; isp-sha256=9f86d0...`, so it can be read back from any chunk
+
+const SHA256_PARAMETER: &str = "isp-sha256";
+const MAC_PARAMETER: &str = "isp-mac";
+const KEY_PARAMETER: &str = "isp-key";
+const ENCODING_PARAMETER: &str = "isp-encoding";
+
+/// Metadata the sdk records for a file it stores, see [`IcspHandle::get_file_meta`](crate::IcspHandle::get_file_meta)
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FileMeta {
+    /// Lowercase hex sha256 of the whole file, not recorded for encrypted files
+    pub sha256: Option<String>,
+    /// Keyed digest of an encrypted file recorded instead of its sha256
+    pub mac: Option<String>,
+    /// Fingerprint of the key the chunks are encrypted with
+    pub key_fingerprint: Option<String>,
+    /// Compression of the stored bytes
+    pub compression: Option<Compression>,
+}
+
+impl FileMeta {
+    // The digest recorded for the content, the keyed one of an encrypted file
+    pub(crate) fn digest(&self) -> Option<&str> {
+        self.mac.as_deref().or(self.sha256.as_deref())
+    }
+}
+
+// Append the metadata to the mime type
+pub(crate) fn encode_file_type(mime: &str, meta: &FileMeta) -> String {
+    let mut file_type = mime.to_string();
+    if let Some(sha256) = &meta.sha256 {
+        file_type.push_str(&format!("; {}={}", SHA256_PARAMETER, sha256));
+    }
+    if let Some(mac) = &meta.mac {
+        file_type.push_str(&format!("; {}={}", MAC_PARAMETER, mac));
+    }
+    if let Some(key_fingerprint) = &meta.key_fingerprint {
+        file_type.push_str(&format!("; {}={}", KEY_PARAMETER, key_fingerprint));
+    }
+    if let Some(compression) = &meta.compression {
+        file_type.push_str(&format!("; {}={}", ENCODING_PARAMETER, compression.name()));
+    }
+    file_type
+}
+
+// Split a stored file_type into the mime type without `isp-` parameters and the metadata
+pub(crate) fn decode_file_type(file_type: &str) -> (String, FileMeta) {
+    let mut meta = FileMeta::default();
+    let mut mime: Vec<&str> = Vec::new();
+    for (position, parameter) in file_type.split(';').map(str::trim).enumerate() {
+        match parameter.split_once('=') {
+            Some((SHA256_PARAMETER, value)) if position > 0 => {
+                meta.sha256 = Some(value.to_string())
+            }
+            Some((MAC_PARAMETER, value)) if position > 0 => meta.mac = Some(value.to_string()),
+            Some((KEY_PARAMETER, value)) if position > 0 => {
+                meta.key_fingerprint = Some(value.to_string())
+            }
+            Some((ENCODING_PARAMETER, value)) if position > 0 => {
+                meta.compression = Compression::from_name(value)
+            }
+            Some((name, _)) if position > 0 && name.starts_with("isp-") => {}
+            _ => mime.push(parameter),
+        }
+    }
+    (mime.join("; "), meta)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn full_meta() -> FileMeta {
+        FileMeta {
+            sha256: None,
+            mac: Some("5d41402a".to_string()),
+            key_fingerprint: Some("9f86d081".to_string()),
+            compression: Some(Compression::Zstd),
+        }
+    }
+
+    #[test]
+    fn plain_mime_round_trips_without_meta() {
+        let file_type = encode_file_type("image/png", &FileMeta::default());
+        assert_eq!(file_type, "image/png");
+        assert_eq!(
+            decode_file_type(&file_type),
+            ("image/png".to_string(), FileMeta::default())
+        );
+    }
+
+    #[test]
+    fn meta_round_trips() {
+        let sha256 = FileMeta {
+            sha256: Some("e3b0c442".to_string()),
+            compression: Some(Compression::Gzip),
+            ..FileMeta::default()
+        };
+        for meta in [sha256, full_meta()] {
+            let file_type = encode_file_type("application/json", &meta);
+            assert_eq!(
+                decode_file_type(&file_type),
+                ("application/json".to_string(), meta)
+            );
+        }
+    }
+
+    #[test]
+    fn foreign_parameters_are_kept_in_the_mime() {
+        let mime = "text/html; charset=utf-8";
+        let file_type = encode_file_type(mime, &full_meta());
+        assert_eq!(
+            file_type,
+            "text/html; charset=utf-8; isp-mac=5d41402a; isp-key=9f86d081; isp-encoding=zstd"
+        );
+        assert_eq!(
+            decode_file_type(&file_type),
+            (mime.to_string(), full_meta())
+        );
+
+        let (mime, meta) = decode_file_type("text/plain;isp-sha256=e3b0c442;charset=utf-8");
+        assert_eq!(mime, "text/plain; charset=utf-8");
+        assert_eq!(meta.sha256.as_deref(), Some("e3b0c442"));
+    }
+
+    #[test]
+    fn unknown_sdk_parameters_are_dropped() {
+        let (mime, meta) = decode_file_type("text/plain; isp-future=1; isp-encoding=brotli");
+        assert_eq!(mime, "text/plain");
+        assert_eq!(meta, FileMeta::default());
+    }
+
+    #[test]
+    fn the_mime_itself_is_never_meta() {
+        let (mime, meta) = decode_file_type("isp-sha256=e3b0c442");
+        assert_eq!(mime, "isp-sha256=e3b0c442");
+        assert_eq!(meta.digest(), None);
+    }
+
+    #[test]
+    fn digest_prefers_the_mac() {
+        let meta = FileMeta {
+            sha256: Some("e3b0c442".to_string()),
+            ..full_meta()
+        };
+        assert_eq!(meta.digest(), Some("5d41402a"));
+        assert_eq!(FileMeta::default().digest(), None);
+    }
+}
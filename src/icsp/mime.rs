@@ -0,0 +1,83 @@
+use std::io;
+use std::path::Path;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+const DEFAULT_FILE_TYPE: &str = "application/octet-stream";
+const SNIFF_SIZE: u64 = 8192;
+
+// Mime type of the file at path from its extension,
+// or sniffed from its first bytes when the extension is missing or unknown
+pub(crate) async fn detect_file_type(
+    path: &Path,
+    file: &mut tokio::fs::File,
+) -> io::Result<String> {
+    if let Some(mime) = mime_guess::from_path(path).first() {
+        return Ok(mime.essence_str().to_string());
+    }
+    let mut head = Vec::new();
+    (&mut *file).take(SNIFF_SIZE).read_to_end(&mut head).await?;
+    file.rewind().await?;
+    Ok(sniff_file_type(&head).to_string())
+}
+
+// Mime type of content from its magic bytes
+pub(crate) fn sniff_file_type(head: &[u8]) -> &'static str {
+    infer::get(head).map_or(DEFAULT_FILE_TYPE, |kind| kind.mime_type())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    // The type detected for a file named with suffix holding content
+    fn detect(suffix: &str, content: &[u8]) -> String {
+        let mut file = tempfile::Builder::new().suffix(suffix).tempfile().unwrap();
+        file.write_all(content).unwrap();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let mut reader = tokio::fs::File::open(file.path()).await.unwrap();
+            let file_type = detect_file_type(file.path(), &mut reader).await.unwrap();
+            assert_eq!(reader.stream_position().await.unwrap(), 0);
+            file_type
+        })
+    }
+
+    #[test]
+    fn detects_types_from_extensions() {
+        for (suffix, mime) in [
+            (".svg", "image/svg+xml"),
+            (
+                ".docx",
+                "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+            ),
+            (
+                ".xlsx",
+                "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+            ),
+            (".json", "application/json"),
+            (".css", "text/css"),
+            (".js", "text/javascript"),
+            (".webp", "image/webp"),
+            (".wasm", "application/wasm"),
+        ] {
+            assert_eq!(detect(suffix, b"content"), mime, "{}", suffix);
+        }
+    }
+
+    #[test]
+    fn sniffs_files_without_extension() {
+        let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+        assert_eq!(detect("", png), "image/png");
+        assert_eq!(detect(".unknown-ext", b"%PDF-1.7\n"), "application/pdf");
+        assert_eq!(sniff_file_type(png), "image/png");
+    }
+
+    #[test]
+    fn falls_back_to_octet_stream() {
+        assert_eq!(detect("", b"plain bytes"), DEFAULT_FILE_TYPE);
+        assert_eq!(sniff_file_type(&[]), DEFAULT_FILE_TYPE);
+    }
+}
@@ -1,41 +1,74 @@
+use crate::bucket::BucketHandle;
+use crate::{ChunkFailure, IspClient, IspError};
 use candid::{Decode, Encode, Nat};
-use garcon::Delay;
-use ic_agent::agent::http_transport::ReqwestHttpReplicaV2Transport;
-use ic_agent::{identity::Secp256k1Identity, Agent};
+use futures::{stream, Stream, StreamExt};
+use ic_agent::Agent;
+use std::collections::HashSet;
 use std::fs::{self};
-use std::path::Path;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tempfile::NamedTempFile;
+use tokio::io::{sink, AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWrite, AsyncWriteExt};
 use uuid::Uuid;
+mod compression;
+mod crypto;
+mod digest;
+mod gc;
 mod icsp_did;
-pub use icsp_did::{Buckets, FileBufExt, StoreArgs};
+mod manifest;
+mod meta;
+mod mime;
+mod progress;
+mod remote_file;
+mod sync;
+use compression::ChunkDecoder;
+pub use compression::Compression;
+pub use crypto::EncryptionKey;
+use crypto::CHUNK_OVERHEAD;
+use digest::{sha256_hex, sha256_reader};
+pub use gc::{DeleteFailure, GarbageCollection, GarbageScan, IncompleteFile, StaleBuffer};
+pub use icsp_did::{
+    BucketStatusExt, Buckets, DataErr, FileBufExt, FileLocation, InitResult, OtherFile, StoreArgs,
+};
+use manifest::{read_manifest, walk_dir, write_manifest};
+pub use manifest::{Deployment, Manifest, ManifestEntry};
+pub use meta::FileMeta;
+use meta::{decode_file_type, encode_file_type};
+use mime::detect_file_type;
+pub use progress::{BatchProgress, Progress};
+use progress::{ProgressCallback, ProgressTracker};
+pub use remote_file::{BlockingRemoteFile, RemoteFile};
+use sha2::{Digest, Sha256};
+pub use sync::{SyncOptions, SyncReport};
 
 const UPDATE_SIZE: usize = 2031616;
+const DEFAULT_UPLOAD_CONCURRENCY: usize = 4;
+const DEFAULT_DOWNLOAD_CONCURRENCY: usize = 4;
 
 /// Get all ic files 's key from user 's icsp
 ///
 /// # Examples
 ///
 /// ``` no_run
-/// use isp_sdk::icsp;
+/// use isp_sdk::{icsp, IspError};
 ///
-/// pub async fn get_all_ic_file_key() {
+/// pub async fn get_all_ic_file_key() -> Result<(), IspError> {
 ///   println!(
 ///     "get all ic file key result: {:?}",
-///     icsp::get_all_ic_file_key("identities/identity.pem", "4radi-oqaaa-aaaan-qapwa-cai").await
+///     icsp::get_all_ic_file_key("identities/identity.pem", "4radi-oqaaa-aaaan-qapwa-cai").await?
 ///  );
+///     Ok(())
 /// }
 /// ```
 pub async fn get_all_ic_file_key(
     pem_identity_path: &str,
     icsp_canister_id_text: &str,
-) -> Vec<String> {
-    let canister_id = candid::Principal::from_text(icsp_canister_id_text).unwrap();
-    let response_blob = build_agent(pem_identity_path)
-        .query(&canister_id, "getAllIcFileKey")
-        .with_arg(Encode!().expect("encode piece failed"))
-        .call()
+) -> Result<Vec<String>, IspError> {
+    build_handle(pem_identity_path, icsp_canister_id_text)?
+        .get_all_ic_file_key()
         .await
-        .expect("response error");
-    Decode!(&response_blob, Vec<String>).unwrap()
 }
 
 /// Get file's information
@@ -43,15 +76,15 @@ pub async fn get_all_ic_file_key(
 /// # Examples
 ///
 /// ``` no_run
-/// use isp_sdk::icsp;
-/// pub async fn get_file_info() {
+/// use isp_sdk::{icsp, IspError};
+/// pub async fn get_file_info() -> Result<(), IspError> {
 ///    println!("get file info result:");
 ///    match icsp::get_file_info(
 ///        "identities/identity.pem",
 ///        "4radi-oqaaa-aaaan-qapwa-cai",
 ///        "49c1dadd-6fa6-4f15-b963-1a1e6f111028".to_string(),
 ///    )
-///        .await
+///        .await?
 ///    {
 ///        None => println!("do not have this file"),
 ///        Some(file_info) => {
@@ -64,21 +97,17 @@ pub async fn get_all_ic_file_key(
 ///            println!("total_size: {:?}", file_info.total_size);
 ///        }
 ///    };
+///     Ok(())
 /// }
 /// ```
 pub async fn get_file_info(
     pem_identity_path: &str,
     icsp_canister_id_text: &str,
     file_key: String,
-) -> Option<FileBufExt> {
-    let canister_id = candid::Principal::from_text(icsp_canister_id_text).unwrap();
-    let response_blob = build_agent(pem_identity_path)
-        .query(&canister_id, "getFileInfo")
-        .with_arg(Encode!(&file_key).expect("encode piece failed"))
-        .call()
+) -> Result<Option<FileBufExt>, IspError> {
+    build_handle(pem_identity_path, icsp_canister_id_text)?
+        .get_file_info(&file_key)
         .await
-        .expect("response error");
-    Decode!(&response_blob, Option<FileBufExt>).unwrap()
 }
 
 /// Get icsp 's cycle balance
@@ -88,24 +117,23 @@ pub async fn get_file_info(
 /// # Examples
 ///
 /// ``` no_run
-/// use isp_sdk::icsp;
+/// use isp_sdk::{icsp, IspError};
 ///
-/// pub async fn get_cycle_balance() {
+/// pub async fn get_cycle_balance() -> Result<(), IspError> {
 ///     println!(
 ///         "icsp cycle balance:{:?}\n",
-///         icsp::get_cycle_balance("identities/identity.pem", "4radi-oqaaa-aaaan-qapwa-cai").await
+///         icsp::get_cycle_balance("identities/identity.pem", "4radi-oqaaa-aaaan-qapwa-cai").await?
 ///     );
+///     Ok(())
 /// }
 /// ```
-pub async fn get_cycle_balance(pem_identity_path: &str, icsp_canister_id_text: &str) -> Nat {
-    let canister_id = candid::Principal::from_text(icsp_canister_id_text).unwrap();
-    let response_blob = build_agent(pem_identity_path)
-        .query(&canister_id, "getCycleBalance")
-        .with_arg(Encode!().expect("encode piece failed"))
-        .call()
+pub async fn get_cycle_balance(
+    pem_identity_path: &str,
+    icsp_canister_id_text: &str,
+) -> Result<Nat, IspError> {
+    build_handle(pem_identity_path, icsp_canister_id_text)?
+        .get_cycle_balance()
         .await
-        .expect("response error");
-    Decode!(&response_blob, Nat).unwrap()
 }
 
 /// Get the bucket where the file is stored
@@ -113,9 +141,9 @@ pub async fn get_cycle_balance(pem_identity_path: &str, icsp_canister_id_text: &
 /// # Examples
 ///
 /// ``` no_run
-/// use isp_sdk::icsp;
+/// use isp_sdk::{icsp, IspError};
 ///
-/// pub async fn get_bucket_of_file() {
+/// pub async fn get_bucket_of_file() -> Result<(), IspError> {
 ///     println!(
 ///         "the file in bucekt:{:?}\n",
 ///         icsp::get_bucket_of_file(
@@ -123,25 +151,21 @@ pub async fn get_cycle_balance(pem_identity_path: &str, icsp_canister_id_text: &
 ///             "4radi-oqaaa-aaaan-qapwa-cai",
 ///             "bf0efa3d-6639-4d62-a81d-c90974cc6925",
 ///         )
-///             .await
+///             .await?
 ///             .expect("no bucket have this file")
 ///             .to_text()
 ///     );
+///     Ok(())
 /// }
 /// ```
 pub async fn get_bucket_of_file(
     pem_identity_path: &str,
     icsp_canister_id_text: &str,
     file_key: &str,
-) -> Option<candid::Principal> {
-    let canister_id = candid::Principal::from_text(icsp_canister_id_text).unwrap();
-    let response_blob = build_agent(pem_identity_path)
-        .query(&canister_id, "getBucketOfFile")
-        .with_arg(Encode!(&file_key).expect("encode piece failed"))
-        .call()
+) -> Result<Option<candid::Principal>, IspError> {
+    build_handle(pem_identity_path, icsp_canister_id_text)?
+        .get_bucket_of_file(file_key)
         .await
-        .expect("response error");
-    Decode!(&response_blob, Option<candid::Principal>).unwrap()
 }
 
 /// Get buckets of user's icsp
@@ -149,11 +173,11 @@ pub async fn get_bucket_of_file(
 /// # Examples
 ///
 /// ```no_run
-/// use isp_sdk::icsp;
+/// use isp_sdk::{icsp, IspError};
 ///
-/// pub async fn get_icsp_buckets() {
+/// pub async fn get_icsp_buckets() -> Result<(), IspError> {
 ///     let response =
-///         icsp::get_icsp_buckets("identities/identity.pem", "4radi-oqaaa-aaaan-qapwa-cai").await;
+///         icsp::get_icsp_buckets("identities/identity.pem", "4radi-oqaaa-aaaan-qapwa-cai").await?;
 ///     match response {
 ///         Some(response) => {
 ///             println!("dead buckets:");
@@ -175,20 +199,16 @@ pub async fn get_bucket_of_file(
 ///         }
 ///         None => println!("icsp do not have buckets"),
 ///     }
+///     Ok(())
 /// }
 /// ```
 pub async fn get_icsp_buckets(
     pem_identity_path: &str,
     icsp_canister_id_text: &str,
-) -> Option<Buckets> {
-    let canister_id = candid::Principal::from_text(icsp_canister_id_text).unwrap();
-    let response_blob = build_agent(pem_identity_path)
-        .query(&canister_id, "getBuckets")
-        .with_arg(Encode!().expect("encode piece failed"))
-        .call()
+) -> Result<Option<Buckets>, IspError> {
+    build_handle(pem_identity_path, icsp_canister_id_text)?
+        .get_icsp_buckets()
         .await
-        .expect("response error");
-    Decode!(&response_blob, Option<Buckets>).unwrap()
 }
 
 /// Get icsp 's admins
@@ -196,27 +216,23 @@ pub async fn get_icsp_buckets(
 /// # Examples
 ///
 /// ``` no_run
-/// use isp_sdk::icsp;
-/// pub async fn get_icsp_admins() {
+/// use isp_sdk::{icsp, IspError};
+/// pub async fn get_icsp_admins() -> Result<(), IspError> {
 ///     println!("icsp admins:");
-///     for i in &icsp::get_icsp_admins("identities/identity.pem", "4radi-oqaaa-aaaan-qapwa-cai").await
+///     for i in &icsp::get_icsp_admins("identities/identity.pem", "4radi-oqaaa-aaaan-qapwa-cai").await?
 ///     {
 ///         println!("{:?}", i.to_text());
 ///     }
+///     Ok(())
 /// }
 /// ```
 pub async fn get_icsp_admins(
     pem_identity_path: &str,
     icsp_canister_id_text: &str,
-) -> Vec<candid::Principal> {
-    let canister_id = candid::Principal::from_text(icsp_canister_id_text).unwrap();
-    let response_blob = build_agent(pem_identity_path)
-        .query(&canister_id, "getAdmins")
-        .with_arg(Encode!().expect("encode error"))
-        .call()
+) -> Result<Vec<candid::Principal>, IspError> {
+    build_handle(pem_identity_path, icsp_canister_id_text)?
+        .get_icsp_admins()
         .await
-        .expect("response error");
-    Decode!(&response_blob, Vec<candid::Principal>).unwrap()
 }
 
 /// Store files from folder_path
@@ -226,9 +242,9 @@ pub async fn get_icsp_admins(
 /// # Examples
 ///
 /// ``` no_run
-/// use isp_sdk::icsp;
+/// use isp_sdk::{icsp, IspError};
 ///
-/// pub async fn store_files() {
+/// pub async fn store_files() -> Result<(), IspError> {
 ///     // url format : icsp_canister_id.raw.ic0.app/'option location'/file_key
 ///     // icsp_canister_id.raw.ic0.app/ic/file_key
 ///     // icsp_canister_id.raw.ic0.app/ipfs/file_key
@@ -239,10 +255,11 @@ pub async fn get_icsp_admins(
 ///         "4radi-oqaaa-aaaan-qapwa-cai",
 ///         true,
 ///     )
-///         .await
+///         .await?
 ///     {
 ///         println!("file_name:{:?},file_key:{:?}", i.0, i.1);
 ///     }
+///     Ok(())
 /// }
 /// ```
 pub async fn store_files(
@@ -250,40 +267,10 @@ pub async fn store_files(
     folder_path: &str,
     icsp_canister_id_text: &str,
     is_http_open: bool,
-) -> Vec<(String, String)> {
-    let canister_id = candid::Principal::from_text(icsp_canister_id_text).unwrap();
-    let agent = build_agent(pem_identity_path);
-
-    let mut ans: Vec<(String, String)> = Vec::new();
-    let paths = fs::read_dir(&folder_path).unwrap();
-    for path in paths {
-        let file_path = path.unwrap().file_name().into_string().unwrap();
-        let pos: Vec<&str> = file_path.split(".").collect();
-        let file_name = String::from(pos[0]);
-        let file_type = String::from(pos[1]);
-        let file_extension = String::from(get_file_type(&file_type));
-        let s = folder_path.to_owned() + &file_path;
-
-        let (file_size, data_slice) = get_file_from_source(&s);
-        let file_key = Uuid::new_v4().to_string();
-        let puts = build_store_args(
-            file_key.clone(),
-            file_extension,
-            file_size.try_into().unwrap(),
-            &data_slice,
-            is_http_open,
-        );
-        for put in &puts {
-            let _response_blob = agent
-                .update(&canister_id, "store")
-                .with_arg(Encode!(put).expect("encode piece failed"))
-                .call_and_wait()
-                .await
-                .expect("response error");
-        }
-        ans.push((file_name.clone(), file_key.clone()));
-    }
-    ans
+) -> Result<Vec<(String, String)>, IspError> {
+    build_handle(pem_identity_path, icsp_canister_id_text)?
+        .store_files(folder_path, is_http_open)
+        .await
 }
 
 /// Store a file from file_path
@@ -295,9 +282,9 @@ pub async fn store_files(
 /// # Examples
 ///
 /// ``` no_run
-/// use isp_sdk::icsp;
+/// use isp_sdk::{icsp, IspError};
 ///
-/// pub async fn store_file() {
+/// pub async fn store_file() -> Result<(), IspError> {
 ///     // url format : icsp_canister_id.raw.ic0.app/'option location'/file_key
 ///     // icsp_canister_id.raw.ic0.app/ic/file_key
 ///     // icsp_canister_id.raw.ic0.app/ipfs/file_key
@@ -308,8 +295,9 @@ pub async fn store_files(
 ///         "4radi-oqaaa-aaaan-qapwa-cai",
 ///         true,
 ///     )
-///         .await;
+///         .await?;
 ///     println!("file_name:{:?},file_key:{:?}", respoonse.0, respoonse.1);
+///     Ok(())
 /// }
 /// ```
 pub async fn store_file(
@@ -317,33 +305,10 @@ pub async fn store_file(
     file_path_str: &str,
     icsp_canister_id_text: &str,
     is_http_open: bool,
-) -> (String, String) {
-    let canister_id = candid::Principal::from_text(icsp_canister_id_text).unwrap();
-    let agent = build_agent(pem_identity_path);
-    let file_path = Path::new(file_path_str);
-    let file_name = file_path.file_stem().unwrap().to_str().unwrap().to_owned();
-    let file_extension = String::from(get_file_type(
-        file_path.extension().unwrap().to_str().unwrap(),
-    ));
-
-    let (file_size, data_slice) = get_file_from_source(file_path_str);
-    let file_key = Uuid::new_v4().to_string();
-    let puts = build_store_args(
-        file_key.clone(),
-        file_extension,
-        file_size.try_into().unwrap(),
-        &data_slice,
-        is_http_open,
-    );
-    for put in &puts {
-        let _response_blob = agent
-            .update(&canister_id, "store")
-            .with_arg(Encode!(put).expect("encode piece failed"))
-            .call_and_wait()
-            .await
-            .expect("response error");
-    }
-    (file_name, file_key.clone())
+) -> Result<(String, String), IspError> {
+    build_handle(pem_identity_path, icsp_canister_id_text)?
+        .store_file(file_path_str, is_http_open)
+        .await
 }
 
 /// Store file with given key
@@ -355,9 +320,9 @@ pub async fn store_file(
 /// # Examples
 ///
 /// ``` no_run
-/// use isp_sdk::icsp;
+/// use isp_sdk::{icsp, IspError};
 ///
-/// pub async fn store_file_by_key() {
+/// pub async fn store_file_by_key() -> Result<(), IspError> {
 ///     let respoonse = icsp::store_file_by_key(
 ///         "identities/identity.pem",
 ///         "source/bitcoin.pdf",
@@ -365,8 +330,9 @@ pub async fn store_file(
 ///         true,
 ///         "test_key".to_string(),
 ///     )
-///         .await;
+///         .await?;
 ///     println!("file_name:{:?},file_key:{:?}", respoonse.0, respoonse.1);
+///     Ok(())
 /// }
 /// ```
 pub async fn store_file_by_key(
@@ -375,32 +341,10 @@ pub async fn store_file_by_key(
     icsp_canister_id_text: &str,
     is_http_open: bool,
     file_key: String,
-) -> (String, String) {
-    let canister_id = candid::Principal::from_text(icsp_canister_id_text).unwrap();
-    let agent = build_agent(pem_identity_path);
-    let file_path = Path::new(file_path_str);
-    let file_name = file_path.file_stem().unwrap().to_str().unwrap().to_owned();
-    let file_extension = String::from(get_file_type(
-        file_path.extension().unwrap().to_str().unwrap(),
-    ));
-
-    let (file_size, data_slice) = get_file_from_source(file_path_str);
-    let puts = build_store_args(
-        file_key.clone(),
-        file_extension,
-        file_size.try_into().unwrap(),
-        &data_slice,
-        is_http_open,
-    );
-    for put in &puts {
-        let _response_blob = agent
-            .update(&canister_id, "store")
-            .with_arg(Encode!(put).expect("encode piece failed"))
-            .call_and_wait()
-            .await
-            .expect("response error");
-    }
-    (file_name, file_key.clone())
+) -> Result<(String, String), IspError> {
+    build_handle(pem_identity_path, icsp_canister_id_text)?
+        .store_file_by_key(file_path_str, is_http_open, file_key)
+        .await
 }
 
 /// Delete file by file_key
@@ -408,27 +352,27 @@ pub async fn store_file_by_key(
 /// # Examples
 ///
 /// ``` no_run
-/// use isp_sdk::icsp;
+/// use isp_sdk::{icsp, IspError};
 ///
-/// pub async fn delete_file() {
+/// pub async fn delete_file() -> Result<(), IspError> {
 ///     let _respoonse = icsp::delete_file(
 ///         "identities/identity.pem",
 ///         "5ekwd-fyaaa-aaaan-qaxlq-cai",
 ///         "64b9eb91-feaa-43f0-aa39-3040c035c5bb",
 ///     )
-///         .await;
+///         .await?;
 ///     println!("complete delete file func");
+///     Ok(())
 /// }
 /// ```
-pub async fn delete_file(pem_identity_path: &str, icsp_canister_id_text: &str, file_key: &str) {
-    let canister_id = candid::Principal::from_text(icsp_canister_id_text).unwrap();
-    let agent = build_agent(pem_identity_path);
-    let _ = agent
-        .update(&canister_id, "delete")
-        .with_arg(Encode!(&file_key.to_string()).expect("encode piece failed"))
-        .call_and_wait()
+pub async fn delete_file(
+    pem_identity_path: &str,
+    icsp_canister_id_text: &str,
+    file_key: &str,
+) -> Result<(), IspError> {
+    build_handle(pem_identity_path, icsp_canister_id_text)?
+        .delete_file(file_key)
         .await
-        .expect("response error");
 }
 
 /// Store str data
@@ -438,9 +382,9 @@ pub async fn delete_file(pem_identity_path: &str, icsp_canister_id_text: &str, f
 /// # Examples
 ///
 /// ``` no_run
-/// use isp_sdk::icsp;
+/// use isp_sdk::{icsp, IspError};
 ///
-/// pub async fn store_str() {
+/// pub async fn store_str() -> Result<(), IspError> {
 ///     // url format : icsp_canister_id.raw.ic0.app/'option location'/file_key
 ///     // icsp_canister_id.raw.ic0.app/ic/file_key
 ///     // icsp_canister_id.raw.ic0.app/ipfs/file_key
@@ -453,8 +397,9 @@ pub async fn delete_file(pem_identity_path: &str, icsp_canister_id_text: &str, f
 ///             "test_isp_sdk_store_str",
 ///             true,
 ///         )
-///             .await
+///             .await?
 ///     );
+///     Ok(())
 /// }
 /// ```
 pub async fn store_str(
@@ -462,38 +407,19 @@ pub async fn store_str(
     icsp_canister_id_text: &str,
     data: &str,
     is_http_open: bool,
-) -> String {
-    let canister_id = candid::Principal::from_text(icsp_canister_id_text).unwrap();
-    let file_extension = "text/plain".to_string();
-    let file_size = data.len();
-    let file_key = Uuid::new_v4().to_string();
-
-    let put = StoreArgs {
-        key: file_key.clone(),
-        value: data.as_bytes().to_owned(),
-        total_index: Nat::from(1),
-        file_type: file_extension.clone(),
-        total_size: file_size.clone() as u64,
-        is_http_open: is_http_open.clone(),
-        index: Nat::from(0),
-    };
-    let _ = build_agent(pem_identity_path)
-        .update(&canister_id, "store")
-        .with_arg(Encode!(&put).expect("encode piece failed"))
-        .call_and_wait()
+) -> Result<String, IspError> {
+    build_handle(pem_identity_path, icsp_canister_id_text)?
+        .store_str(data, is_http_open)
         .await
-        .expect("response error");
-
-    file_key
 }
 
-/// Replace the value str corresponding to the key
+/// Replace the value str corresponding to the key, see [`IcspHandle::replace_bytes`]
 /// # Examples
 ///
 /// ``` no_run
-/// use isp_sdk::icsp;
+/// use isp_sdk::{icsp, IspError};
 ///
-/// pub async fn replace_str() {
+/// pub async fn replace_str() -> Result<(), IspError> {
 ///     icsp::replace_str(
 ///         "identities/identity.pem",
 ///         "4radi-oqaaa-aaaan-qapwa-cai",
@@ -501,8 +427,9 @@ pub async fn store_str(
 ///         "test_isp_sdk_replace_str",
 ///         true,
 ///     )
-///         .await;
+///         .await?;
 ///     println!("replace_str complete ");
+///     Ok(())
 /// }
 /// ```
 pub async fn replace_str(
@@ -511,25 +438,10 @@ pub async fn replace_str(
     file_key: &str,
     data: &str,
     is_http_open: bool,
-) {
-    delete_file(pem_identity_path, icsp_canister_id_text, file_key).await;
-
-    let canister_id = candid::Principal::from_text(icsp_canister_id_text).unwrap();
-    let put = StoreArgs {
-        key: file_key.to_string().to_owned(),
-        value: data.as_bytes().to_owned(),
-        total_index: Nat::from(1),
-        file_type: "text/plain".to_string().clone(),
-        total_size: data.len().clone() as u64,
-        is_http_open: is_http_open.clone(),
-        index: Nat::from(0),
-    };
-    let _ = build_agent(pem_identity_path)
-        .update(&canister_id, "store")
-        .with_arg(Encode!(&put).expect("encode piece failed"))
-        .call_and_wait()
+) -> Result<Option<String>, IspError> {
+    build_handle(pem_identity_path, icsp_canister_id_text)?
+        .replace_str(file_key, data, is_http_open)
         .await
-        .expect("response error");
 }
 
 /// Get file from icsp, return (data, file_type)
@@ -537,15 +449,15 @@ pub async fn replace_str(
 /// # Examples
 ///
 /// ``` no_run
-/// use isp_sdk::icsp;
+/// use isp_sdk::{icsp, IspError};
 /// use std::io::Write;
-/// pub async fn get_file() {
+/// pub async fn get_file() -> Result<(), IspError> {
 ///     let response = icsp::get_file(
 ///         "identities/identity.pem",
 ///         "4radi-oqaaa-aaaan-qapwa-cai",
 ///         "3166112af0dcc940f8e7f2199a4200cfb5e2efb40796391201b8fe9e4ff7ca84",
 ///     )
-///         .await;
+///         .await?;
 ///
 ///     let mut file = std::fs::File::create("output/bitcoin.pdf").expect("create failed");
 ///     file.write_all(&response.0).expect("write failed");
@@ -554,55 +466,17 @@ pub async fn replace_str(
 ///         "file out put at folder output/ , file_type:{:?}",
 ///         response.1
 ///     );
+///     Ok(())
 /// }
 /// ```
 pub async fn get_file(
     pem_identity_path: &str,
     icsp_canister_id_text: &str,
     file_key: &str,
-) -> (Vec<u8>, String) {
-    let bucket_canister_id = get_bucket_of_file(pem_identity_path, icsp_canister_id_text, file_key)
-        .await
-        .expect("can not find bucket have this file");
-    let agent = build_agent(pem_identity_path);
-
-    let total_index_blob = agent
-        .update(
-            &candid::Principal::from_text(bucket_canister_id.to_text()).unwrap(),
-            "getFileTotalIndex",
-        )
-        .with_arg(Encode!(&file_key).expect("encode failed"))
-        .call_and_wait()
+) -> Result<(Vec<u8>, String), IspError> {
+    build_handle(pem_identity_path, icsp_canister_id_text)?
+        .get_file(file_key)
         .await
-        .expect("response error");
-    let total_index = Decode!(&total_index_blob, Nat).unwrap();
-    if total_index < Nat::from(1) {
-        return (vec![], "".to_string());
-    }
-    let mut index = 0;
-    let mut payload: Vec<u8> = Vec::new();
-    let mut file_type = "".to_string();
-    while Nat::from(index) < total_index {
-        let response_blob = agent
-            .query(
-                &candid::Principal::from_text(bucket_canister_id.to_text()).unwrap(),
-                "get",
-            )
-            .with_arg(Encode!(&file_key, &Nat::from(index)).expect("encode failed"))
-            .call()
-            .await
-            .expect("response error");
-        let mut response = Decode!(&response_blob, Option<(Vec<u8>, String)>)
-            .unwrap()
-            .expect("assets not have this file");
-        payload.append(&mut response.0);
-        index += 1;
-        if Nat::from(index) == total_index {
-            file_type = response.1;
-        }
-    }
-
-    (payload, file_type)
 }
 
 /// Add admin of icsp
@@ -610,30 +484,27 @@ pub async fn get_file(
 /// # Examples
 ///
 /// ``` no_run
-/// use isp_sdk::icsp;
+/// use isp_sdk::{icsp, IspError};
 ///
-/// pub async fn add_icsp_admin() {
+/// pub async fn add_icsp_admin() -> Result<(), IspError> {
 ///     icsp::add_icsp_admin(
 ///         "identities/identity.pem",
 ///         "4radi-oqaaa-aaaan-qapwa-cai",
 ///         "bxgws-37y5d-tgmpr-hekbp-y3uxo-yicgs-fo7p3-ccnta-kidrz-74onh-pae",
 ///     )
-///         .await
+///         .await?;
+///     Ok(())
 /// }
 /// ```
 pub async fn add_icsp_admin(
     pem_identity_path: &str,
     icsp_canister_id_text: &str,
     new_admin_text: &str,
-) {
-    let canister_id = candid::Principal::from_text(icsp_canister_id_text).unwrap();
-    let new_admin = candid::Principal::from_text(new_admin_text).unwrap();
-    let _ = build_agent(pem_identity_path)
-        .update(&canister_id, "addAdmin")
-        .with_arg(Encode!(&new_admin).expect("encode error"))
-        .call_and_wait()
+) -> Result<(), IspError> {
+    let new_admin = candid::Principal::from_text(new_admin_text)?;
+    build_handle(pem_identity_path, icsp_canister_id_text)?
+        .add_icsp_admin(new_admin)
         .await
-        .expect("response error");
 }
 
 /// Delete admin of icsp
@@ -641,30 +512,27 @@ pub async fn add_icsp_admin(
 /// # Examples
 ///
 /// ``` no_run
-/// use isp_sdk::icsp;
+/// use isp_sdk::{icsp, IspError};
 ///
-/// pub async fn delete_icsp_admin() {
+/// pub async fn delete_icsp_admin() -> Result<(), IspError> {
 ///     icsp::delete_icsp_admin(
 ///         "identities/identity.pem",
 ///         "4radi-oqaaa-aaaan-qapwa-cai",
 ///         "bxgws-37y5d-tgmpr-hekbp-y3uxo-yicgs-fo7p3-ccnta-kidrz-74onh-pae",
 ///     )
-///         .await
+///         .await?;
+///     Ok(())
 /// }
 /// ```
 pub async fn delete_icsp_admin(
     pem_identity_path: &str,
     icsp_canister_id_text: &str,
     old_admin_text: &str,
-) {
-    let canister_id = candid::Principal::from_text(icsp_canister_id_text).unwrap();
-    let old_admin = candid::Principal::from_text(old_admin_text).unwrap();
-    let _ = build_agent(pem_identity_path)
-        .update(&canister_id, "deleteAdmin")
-        .with_arg(Encode!(&old_admin).expect("encode error"))
-        .call_and_wait()
+) -> Result<(), IspError> {
+    let old_admin = candid::Principal::from_text(old_admin_text)?;
+    build_handle(pem_identity_path, icsp_canister_id_text)?
+        .delete_icsp_admin(old_admin)
         .await
-        .expect("response error");
 }
 
 /// Top up every bucket some Cycles by using icsp's Cycles
@@ -672,27 +540,28 @@ pub async fn delete_icsp_admin(
 /// # Examples
 ///
 /// ``` no_run
-/// use isp_sdk::icsp;
+/// use isp_sdk::{icsp, IspError};
 ///
-/// pub async fn top_up_bucket() {
+/// pub async fn top_up_bucket() -> Result<(), IspError> {
 ///     // 0.1 T Cycles
 ///     icsp::top_up_bucket(
 ///         "identities/identity.pem",
 ///         "4radi-oqaaa-aaaan-qapwa-cai",
 ///         100_000_000_000 as u64,
 ///     )
-///         .await;
+///         .await?;
 ///     println!("complete top_up_bucket func, top up every bucket 0.1 T Cycles");
+///     Ok(())
 /// }
 /// ```
-pub async fn top_up_bucket(pem_identity_path: &str, icsp_canister_id_text: &str, amount: u64) {
-    let canister_id = candid::Principal::from_text(icsp_canister_id_text).unwrap();
-    let _ = build_agent(pem_identity_path)
-        .update(&canister_id, "topUpBucket")
-        .with_arg(Encode!(&Nat::from(amount)).expect("encode error"))
-        .call_and_wait()
+pub async fn top_up_bucket(
+    pem_identity_path: &str,
+    icsp_canister_id_text: &str,
+    amount: u64,
+) -> Result<(), IspError> {
+    build_handle(pem_identity_path, icsp_canister_id_text)?
+        .top_up_bucket(amount)
         .await
-        .expect("response error");
 }
 
 /// Get ICSP's WASM version
@@ -700,24 +569,23 @@ pub async fn top_up_bucket(pem_identity_path: &str, icsp_canister_id_text: &str,
 /// # Examples
 ///
 /// ``` no_run
-/// use isp_sdk::icsp;
+/// use isp_sdk::{icsp, IspError};
 ///
-/// pub async fn get_icsp_version() {
+/// pub async fn get_icsp_version() -> Result<(), IspError> {
 ///     println!(
 ///         "icsp version: {:?}",
-///         icsp::get_version("identities/identity.pem", "4radi-oqaaa-aaaan-qapwa-cai").await
+///         icsp::get_version("identities/identity.pem", "4radi-oqaaa-aaaan-qapwa-cai").await?
 ///     );
+///     Ok(())
 /// }
 /// ```
-pub async fn get_version(pem_identity_path: &str, icsp_canister_id_text: &str) -> String {
-    let canister_id = candid::Principal::from_text(icsp_canister_id_text).unwrap();
-    let response_blob = build_agent(pem_identity_path)
-        .query(&canister_id, "getVersion")
-        .with_arg(Encode!().expect("encode error"))
-        .call()
+pub async fn get_version(
+    pem_identity_path: &str,
+    icsp_canister_id_text: &str,
+) -> Result<String, IspError> {
+    build_handle(pem_identity_path, icsp_canister_id_text)?
+        .get_version()
         .await
-        .expect("response error");
-    Decode!(&response_blob, String).unwrap()
 }
 
 /// Query the number of ic files stored in icsp
@@ -725,27 +593,23 @@ pub async fn get_version(pem_identity_path: &str, icsp_canister_id_text: &str) -
 /// # Examples
 ///
 /// ``` no_run
-/// use isp_sdk::icsp;
+/// use isp_sdk::{icsp, IspError};
 ///
-/// pub async fn get_ic_file_numbers(pem_identity_path: &str, icsp_canister_id_text: &str) -> Nat {
+/// pub async fn get_ic_file_numbers() -> Result<(), IspError> {
 ///     println!(
 ///         "icsp 's ic file numbers: {:?}",
-///         icsp::get_ic_file_numbers("identities/identity.pem", "4radi-oqaaa-aaaan-qapwa-cai").await
+///         icsp::get_ic_file_numbers("identities/identity.pem", "4radi-oqaaa-aaaan-qapwa-cai").await?
 ///     );
+///     Ok(())
 /// }
 /// ```
 pub async fn get_ic_file_numbers(
     pem_identity_path: &str,
     icsp_canister_id_text: &str,
-) -> Option<Nat> {
-    let canister_id = candid::Principal::from_text(icsp_canister_id_text).unwrap();
-    let response_blob = build_agent(pem_identity_path)
-        .query(&canister_id, "getIcFileNums")
-        .with_arg(Encode!().expect("encode error"))
-        .call()
+) -> Result<Option<Nat>, IspError> {
+    build_handle(pem_identity_path, icsp_canister_id_text)?
+        .get_ic_file_numbers()
         .await
-        .expect("response error");
-    Decode!(&response_blob, Option<Nat>).unwrap()
 }
 
 /// Slice all files by page_number and return the information of file_info at page_index
@@ -753,9 +617,9 @@ pub async fn get_ic_file_numbers(
 /// # Examples
 ///
 /// ``` no_run
-/// use isp_sdk::icsp;
+/// use isp_sdk::{icsp, IspError};
 ///
-/// pub async fn get_field_file_infos() {
+/// pub async fn get_field_file_infos() -> Result<(), IspError> {
 ///     let page_num: u64 = 10;
 ///     let page_index: u64 = 2;
 ///     println!(
@@ -769,7 +633,7 @@ pub async fn get_ic_file_numbers(
 ///         page_num,
 ///         page_index,
 ///     )
-///         .await
+///         .await?
 ///     {
 ///         index += 1;
 ///         println!("the file_info index: {:?}", index);
@@ -782,6 +646,7 @@ pub async fn get_ic_file_numbers(
 ///         println!("total_size: {:?}", file_info.total_size);
 ///         println!("\n");
 ///     }
+///     Ok(())
 /// }
 /// ```
 pub async fn get_field_file_infos(
@@ -789,111 +654,2281 @@ pub async fn get_field_file_infos(
     icsp_canister_id_text: &str,
     page_number: u64,
     page_index: u64,
-) -> Vec<FileBufExt> {
-    let canister_id = candid::Principal::from_text(icsp_canister_id_text).unwrap();
-    let response_blob = build_agent(pem_identity_path)
-        .query(&canister_id, "getFieldFileInfos")
-        .with_arg(Encode!(&Nat::from(page_number), &Nat::from(page_index)).expect("encode error"))
-        .call()
+) -> Result<Vec<FileBufExt>, IspError> {
+    build_handle(pem_identity_path, icsp_canister_id_text)?
+        .get_field_file_infos(page_number, page_index)
+        .await
+}
+
+/// Record a file stored on IPFS or Arweave
+///
+/// url format: icsp_canister_id.raw.ic0.app/ipfs/file_key or icsp_canister_id.raw.ic0.app/ar/file_key
+///
+/// # Examples
+///
+/// ``` no_run
+/// use isp_sdk::icsp::{self, FileLocation, OtherFile};
+/// use isp_sdk::IspError;
+///
+/// pub async fn record_file() -> Result<(), IspError> {
+///     icsp::record_file(
+///         "identities/identity.pem",
+///         "4radi-oqaaa-aaaan-qapwa-cai",
+///         OtherFile {
+///             file_location: FileLocation::IPFS,
+///             file_key: "bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi".to_string(),
+///             file_url: "https://ipfs.io/ipfs/bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi"
+///                 .to_string(),
+///             file_type: "image/png".to_string(),
+///         },
+///     )
+///     .await?;
+///     Ok(())
+/// }
+/// ```
+pub async fn record_file(
+    pem_identity_path: &str,
+    icsp_canister_id_text: &str,
+    other_file: OtherFile,
+) -> Result<(), IspError> {
+    build_handle(pem_identity_path, icsp_canister_id_text)?
+        .record_file(other_file)
+        .await
+}
+
+/// Get the IPFS or Arweave file recorded with file_key
+///
+/// # Examples
+///
+/// ``` no_run
+/// use isp_sdk::icsp::{self, FileLocation};
+/// use isp_sdk::IspError;
+///
+/// pub async fn get_other_file() -> Result<(), IspError> {
+///     match icsp::get_other_file(
+///         "identities/identity.pem",
+///         "4radi-oqaaa-aaaan-qapwa-cai",
+///         "bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi",
+///         FileLocation::IPFS,
+///     )
+///     .await?
+///     {
+///         None => println!("do not have this file"),
+///         Some(other_file) => {
+///             println!("file_url: {:?}", other_file.file_url);
+///             println!("file type: {:?}", other_file.file_type);
+///         }
+///     }
+///     Ok(())
+/// }
+/// ```
+pub async fn get_other_file(
+    pem_identity_path: &str,
+    icsp_canister_id_text: &str,
+    file_key: &str,
+    file_location: FileLocation,
+) -> Result<Option<OtherFile>, IspError> {
+    build_handle(pem_identity_path, icsp_canister_id_text)?
+        .get_other_file(file_key, file_location)
         .await
-        .expect("response error");
-    Decode!(&response_blob, Vec<FileBufExt>).unwrap()
-}
-
-fn get_waiter() -> Delay {
-    let waiter = garcon::Delay::builder()
-        .throttle(std::time::Duration::from_millis(500))
-        .timeout(std::time::Duration::from_secs(60 * 5))
-        .build();
-    waiter
-}
-
-fn build_agent(pem_identity_path: &str) -> Agent {
-    let url = "https://ic0.app".to_string();
-    let identity = Secp256k1Identity::from_pem_file(String::from(pem_identity_path)).unwrap();
-    let transport = ReqwestHttpReplicaV2Transport::create(url).expect("transport error");
-    let agent = Agent::builder()
-        .with_transport(transport)
-        .with_identity(identity)
-        .build()
-        .expect("build agent error");
-    agent
-}
-
-// Access file from file path, slice and return [each slice] array
-fn get_file_from_source(path: &str) -> (usize, Vec<Vec<u8>>) {
-    let context = fs::read(path).expect("read file failed");
-    let size = context.len();
-    let slice_size = if context.len() % UPDATE_SIZE == 0 {
-        context.len() / UPDATE_SIZE
-    } else {
-        context.len() / UPDATE_SIZE + 1
+}
+
+/// Get all IPFS files 's key from user 's icsp
+///
+/// # Examples
+///
+/// ``` no_run
+/// use isp_sdk::{icsp, IspError};
+///
+/// pub async fn get_all_ipfs_file_key() -> Result<(), IspError> {
+///     println!(
+///         "get all ipfs file key result: {:?}",
+///         icsp::get_all_ipfs_file_key("identities/identity.pem", "4radi-oqaaa-aaaan-qapwa-cai")
+///             .await?
+///     );
+///     Ok(())
+/// }
+/// ```
+pub async fn get_all_ipfs_file_key(
+    pem_identity_path: &str,
+    icsp_canister_id_text: &str,
+) -> Result<Vec<String>, IspError> {
+    build_handle(pem_identity_path, icsp_canister_id_text)?
+        .get_all_ipfs_file_key()
+        .await
+}
+
+/// Get all Arweave files 's key from user 's icsp
+///
+/// # Examples
+///
+/// ``` no_run
+/// use isp_sdk::{icsp, IspError};
+///
+/// pub async fn get_all_ar_file_key() -> Result<(), IspError> {
+///     println!(
+///         "get all arweave file key result: {:?}",
+///         icsp::get_all_ar_file_key("identities/identity.pem", "4radi-oqaaa-aaaan-qapwa-cai")
+///             .await?
+///     );
+///     Ok(())
+/// }
+/// ```
+pub async fn get_all_ar_file_key(
+    pem_identity_path: &str,
+    icsp_canister_id_text: &str,
+) -> Result<Vec<String>, IspError> {
+    build_handle(pem_identity_path, icsp_canister_id_text)?
+        .get_all_ar_file_key()
+        .await
+}
+
+/// A handle bound to one icsp canister, created by [`IspClient::icsp`]
+///
+/// Every call reuses the agent of the client that created the handle.
+///
+/// The chunks of a file are stored with up to [`upload_concurrency`](Self::upload_concurrency)
+/// `store` calls in flight, chunks that fail are reported by [`IspError::Upload`].
+/// Downloads fetch up to [`download_concurrency`](Self::download_concurrency) chunks at once.
+/// Files stored from a path, str or bytes record their sha256 in the file_type, `get_file` verifies it.
+///
+/// # Examples
+///
+/// ``` no_run
+/// use candid::Principal;
+/// use isp_sdk::{IspClient, IspError};
+///
+/// pub async fn store_video(client: &IspClient) -> Result<(), IspError> {
+///     let icsp = client
+///         .icsp(Principal::from_text("4radi-oqaaa-aaaan-qapwa-cai")?)
+///         .with_upload_concurrency(16);
+///     match icsp.store_file("source/video.mp4", true).await {
+///         Ok((_, file_key)) => println!("file_key:{:?}", file_key),
+///         Err(IspError::Upload { file_key, failures }) => {
+///             for failure in failures {
+///                 println!("chunk {} of {} error: {}", failure.index, file_key, failure.error);
+///             }
+///         }
+///         Err(error) => return Err(error),
+///     }
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone)]
+pub struct IcspHandle {
+    agent: Agent,
+    canister_id: candid::Principal,
+    upload_concurrency: usize,
+    download_concurrency: usize,
+    progress: Option<ProgressCallback>,
+    content_addressed: bool,
+    encryption_key: Option<EncryptionKey>,
+    compression: Option<Compression>,
+}
+
+impl IcspHandle {
+    pub(crate) fn new(agent: Agent, canister_id: candid::Principal) -> IcspHandle {
+        IcspHandle {
+            agent,
+            canister_id,
+            upload_concurrency: DEFAULT_UPLOAD_CONCURRENCY,
+            download_concurrency: DEFAULT_DOWNLOAD_CONCURRENCY,
+            progress: None,
+            content_addressed: false,
+            encryption_key: None,
+            compression: None,
+        }
+    }
+
+    /// Store up to upload_concurrency chunks at the same time, 4 by default and at least 1
+    pub fn with_upload_concurrency(mut self, upload_concurrency: usize) -> Self {
+        self.upload_concurrency = upload_concurrency.max(1);
+        self
+    }
+
+    /// Get up to download_concurrency chunks at the same time, 4 by default and at least 1
+    pub fn with_download_concurrency(mut self, download_concurrency: usize) -> Self {
+        self.download_concurrency = download_concurrency.max(1);
+        self
+    }
+
+    /// Report the [`Progress`] of every file stored or got by this handle after every chunk
+    ///
+    /// The callback runs on the task making the calls, send the progress to a channel to
+    /// consume it somewhere else.
+    ///
+    /// # Examples
+    ///
+    /// ``` no_run
+    /// use isp_sdk::{IcspHandle, IspError};
+    /// use std::sync::mpsc;
+    ///
+    /// pub async fn store_files(icsp: &IcspHandle) -> Result<(), IspError> {
+    ///     let (sender, receiver) = mpsc::channel();
+    ///     std::thread::spawn(move || {
+    ///         for (files_done, bytes_done, total_bytes) in receiver {
+    ///             println!("{} files, {}/{} bytes", files_done, bytes_done, total_bytes);
+    ///         }
+    ///     });
+    ///     let icsp = icsp.clone().with_progress(move |progress| {
+    ///         if let Some(batch) = progress.batch {
+    ///             let _ = sender.send((batch.files_done, batch.bytes_done, batch.total_bytes));
+    ///         }
+    ///     });
+    ///     icsp.store_files("source/", true).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn with_progress<F: Fn(&Progress) + Send + Sync + 'static>(mut self, callback: F) -> Self {
+        self.progress = Some(Arc::new(callback));
+        self
+    }
+
+    /// Use the lowercase hex sha256 of the content as file_key instead of a random uuid
+    ///
+    /// With an encryption key the file_key is the keyed digest of the content instead, so that
+    /// the key does not reveal the content, see [`EncryptionKey`].
+    ///
+    /// `store_file`, `store_files`, `store_str` and `store_bytes` then skip files the icsp already has and
+    /// resume files the icsp has partially received, the http open flag of an existing file
+    /// is not changed.
+    ///
+    /// # Examples
+    ///
+    /// ``` no_run
+    /// use isp_sdk::{IcspHandle, IspError};
+    ///
+    /// pub async fn store_asset(icsp: &IcspHandle) -> Result<(), IspError> {
+    ///     let icsp = icsp.clone().with_content_addressed(true);
+    ///     let (_, file_key) = icsp.store_file("assets/logo.png", true).await?;
+    ///     // stores nothing, the icsp already has the file
+    ///     let (_, same_file_key) = icsp.store_file("assets/logo.png", true).await?;
+    ///     assert_eq!(file_key, same_file_key);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn with_content_addressed(mut self, content_addressed: bool) -> Self {
+        self.content_addressed = content_addressed;
+        self
+    }
+
+    /// Encrypt the files stored by this handle with the key and decrypt the files got
+    ///
+    /// Files stored without a key are still got as they are. Getting an encrypted file returns
+    /// [`IspError::EncryptionKeyMissing`] without a key and [`IspError::WrongEncryptionKey`]
+    /// with another key.
+    ///
+    /// # Examples
+    ///
+    /// ``` no_run
+    /// use isp_sdk::icsp::EncryptionKey;
+    /// use isp_sdk::{IcspHandle, IspError};
+    ///
+    /// pub async fn store_private(icsp: &IcspHandle, key_bytes: [u8; 32]) -> Result<(), IspError> {
+    ///     let icsp = icsp
+    ///         .clone()
+    ///         .with_encryption_key(EncryptionKey::from_bytes(key_bytes));
+    ///     let (_, file_key) = icsp.store_file("private/contract.pdf", false).await?;
+    ///     let (data, file_type) = icsp.get_file(&file_key).await?;
+    ///     println!("get {:?} bytes, file_type:{:?}", data.len(), file_type);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn with_encryption_key(mut self, encryption_key: EncryptionKey) -> Self {
+        self.encryption_key = Some(encryption_key);
+        self
+    }
+
+    /// Compress the files stored from a path, str or bytes by this handle before they are chunked
+    ///
+    /// Files got by any handle are decompressed and keep their original file_type.
+    /// Compressed files can not be read with [`read_range`](Self::read_range) or
+    /// [`open_file`](Self::open_file). The gateway serves the stored bytes without a
+    /// Content-Encoding header, so storing a file http open with this handle returns
+    /// [`IspError::CompressedHttpOpen`].
+    ///
+    /// # Examples
+    ///
+    /// ``` no_run
+    /// use isp_sdk::icsp::Compression;
+    /// use isp_sdk::{IcspHandle, IspError};
+    ///
+    /// pub async fn store_logs(icsp: &IcspHandle) -> Result<(), IspError> {
+    ///     let icsp = icsp.clone().with_compression(Compression::Zstd);
+    ///     let (_, file_key) = icsp.store_file("logs/service.json", false).await?;
+    ///     let (data, file_type) = icsp.get_file(&file_key).await?;
+    ///     println!("get {:?} bytes, file_type:{:?}", data.len(), file_type);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+
+    /// The icsp canister this handle is bound to
+    pub fn canister_id(&self) -> candid::Principal {
+        self.canister_id
+    }
+
+    /// The maximum number of `store` calls in flight for one file
+    pub fn upload_concurrency(&self) -> usize {
+        self.upload_concurrency
+    }
+
+    /// The maximum number of `get` queries in flight for one file
+    pub fn download_concurrency(&self) -> usize {
+        self.download_concurrency
+    }
+
+    /// Get all ic files 's key from user 's icsp
+    pub async fn get_all_ic_file_key(&self) -> Result<Vec<String>, IspError> {
+        let response_blob = self
+            .agent
+            .query(&self.canister_id, "getAllIcFileKey")
+            .with_arg(Encode!()?)
+            .call()
+            .await?;
+        Ok(Decode!(&response_blob, Vec<String>)?)
+    }
+
+    /// Get file's information
+    ///
+    /// The file_type is the mime type the file was stored as, without the metadata the sdk records
+    /// in the stored file_type, get it with [`IcspHandle::get_file_meta`].
+    pub async fn get_file_info(&self, file_key: &str) -> Result<Option<FileBufExt>, IspError> {
+        Ok(self.raw_file_info(file_key).await?.map(strip_file_info))
+    }
+
+    /// Get the metadata the sdk recorded for the file when it was stored
+    ///
+    /// The metadata is recorded as `isp-` parameters of the stored file_type, e.g.
+    /// `application/pdf; isp-sha256=9f86d0...`. The gateway serves the stored file_type as the
+    /// Content-Type header of http open files, browsers ignore the unknown parameters.
+    ///
+    /// # Examples
+    ///
+    /// ``` no_run
+    /// use isp_sdk::{IcspHandle, IspError};
+    ///
+    /// pub async fn print_digest(icsp: &IcspHandle, file_key: &str) -> Result<(), IspError> {
+    ///     if let Some(meta) = icsp.get_file_meta(file_key).await? {
+    ///         println!("sha256:{:?}, encrypted:{}", meta.sha256, meta.key_fingerprint.is_some());
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn get_file_meta(&self, file_key: &str) -> Result<Option<FileMeta>, IspError> {
+        Ok(self
+            .raw_file_info(file_key)
+            .await?
+            .map(|file_info| decode_file_type(&file_info.file_type).1))
+    }
+
+    // The file information as stored, file_type keeps the metadata
+    async fn raw_file_info(&self, file_key: &str) -> Result<Option<FileBufExt>, IspError> {
+        let response_blob = self
+            .agent
+            .query(&self.canister_id, "getFileInfo")
+            .with_arg(Encode!(&file_key)?)
+            .call()
+            .await?;
+        Ok(Decode!(&response_blob, Option<FileBufExt>)?)
+    }
+
+    /// Get icsp 's cycle balance, the cycle balance is e12s
+    pub async fn get_cycle_balance(&self) -> Result<Nat, IspError> {
+        let response_blob = self
+            .agent
+            .query(&self.canister_id, "getCycleBalance")
+            .with_arg(Encode!()?)
+            .call()
+            .await?;
+        Ok(Decode!(&response_blob, Nat)?)
+    }
+
+    /// Get the bucket where the file is stored
+    pub async fn get_bucket_of_file(
+        &self,
+        file_key: &str,
+    ) -> Result<Option<candid::Principal>, IspError> {
+        let response_blob = self
+            .agent
+            .query(&self.canister_id, "getBucketOfFile")
+            .with_arg(Encode!(&file_key)?)
+            .call()
+            .await?;
+        Ok(Decode!(&response_blob, Option<candid::Principal>)?)
+    }
+
+    /// Get buckets of user's icsp
+    pub async fn get_icsp_buckets(&self) -> Result<Option<Buckets>, IspError> {
+        let response_blob = self
+            .agent
+            .query(&self.canister_id, "getBuckets")
+            .with_arg(Encode!()?)
+            .call()
+            .await?;
+        Ok(Decode!(&response_blob, Option<Buckets>)?)
+    }
+
+    /// Find the buffers and incomplete files left in the icsp and its live buckets by failed uploads
+    ///
+    /// Uploads still running are found too, pass the scan to [`IcspHandle::collect_garbage`]
+    /// to delete only what made no progress since.
+    pub async fn scan_garbage(&self) -> Result<GarbageScan, IspError> {
+        let mut scan = GarbageScan::new();
+        let live_buckets = self
+            .get_icsp_buckets()
+            .await?
+            .map_or_else(Vec::new, |buckets| buckets.live_buckets);
+        for bucket in live_buckets {
+            let bucket_canister_id = bucket.canister_id;
+            let buffers = self.bucket(bucket_canister_id).get_buffers().await?;
+            scan.buffers
+                .extend(buffers.into_iter().map(|file_key| StaleBuffer {
+                    bucket_canister_id,
+                    file_key,
+                }));
+        }
+
+        let mut file_infos = stream::iter(self.get_all_ic_file_key().await?)
+            .map(|file_key| async move {
+                let file_info = self.raw_file_info(&file_key).await?;
+                Ok::<_, IspError>((file_key, file_info))
+            })
+            .buffered(self.download_concurrency);
+        while let Some((file_key, file_info)) = file_infos.next().await.transpose()? {
+            match file_info {
+                Some(file_info) if file_info.received < file_info.total_index => {
+                    scan.incomplete_files.push(IncompleteFile {
+                        file_key,
+                        bucket_canister_id: file_info.bucket_id,
+                        received: file_info.received,
+                        total_index: file_info.total_index,
+                    })
+                }
+                _ => {}
+            }
+        }
+        Ok(scan)
+    }
+
+    /// Delete what scan found that is still there and made no progress, return what was deleted
+    ///
+    /// Nothing is deleted until min_age has passed since the scan, so uploads that are only slow
+    /// are kept. Pass `Duration::ZERO` to delete as soon as the scan has been confirmed.
+    /// A file or buffer that can not be deleted is reported in the failures of the result and
+    /// the others are still deleted.
+    ///
+    /// # Examples
+    ///
+    /// ``` no_run
+    /// use isp_sdk::{IcspHandle, IspError};
+    /// use std::time::Duration;
+    ///
+    /// pub async fn collect_garbage(icsp: &IcspHandle) -> Result<(), IspError> {
+    ///     let scan = icsp.scan_garbage().await?;
+    ///     println!("buffers:{:?}", scan.buffers);
+    ///     println!("incomplete files:{:?}", scan.incomplete_files);
+    ///     tokio::time::sleep(Duration::from_secs(3600)).await;
+    ///     let collection = icsp
+    ///         .collect_garbage(&scan, Duration::from_secs(3600))
+    ///         .await?;
+    ///     println!("deleted:{:?}", collection.deleted);
+    ///     for failure in collection.failures {
+    ///         println!("delete {} error:{}", failure.file_key, failure.error);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn collect_garbage(
+        &self,
+        scan: &GarbageScan,
+        min_age: Duration,
+    ) -> Result<GarbageCollection, IspError> {
+        let mut collection = GarbageCollection {
+            deleted: GarbageScan::new(),
+            failures: Vec::new(),
+        };
+        if scan.scanned_at.elapsed().unwrap_or_default() < min_age {
+            return Ok(collection);
+        }
+        let stalled = self.scan_garbage().await?.stalled_since(scan);
+        for file in stalled.incomplete_files {
+            match self.delete_file(&file.file_key).await {
+                Ok(()) => collection.deleted.incomplete_files.push(file),
+                Err(error) => collection.failures.push(DeleteFailure {
+                    file_key: file.file_key,
+                    error,
+                }),
+            }
+        }
+        // deleting a file may have deleted its buffer already
+        for buffer in stalled.buffers {
+            let bucket = self.bucket(buffer.bucket_canister_id);
+            match bucket.delete(&buffer.file_key).await {
+                Ok(true) => collection.deleted.buffers.push(buffer),
+                Ok(false) => {}
+                Err(error) => collection.failures.push(DeleteFailure {
+                    file_key: buffer.file_key,
+                    error,
+                }),
+            }
+        }
+        Ok(collection)
+    }
+
+    /// Get icsp 's admins
+    pub async fn get_icsp_admins(&self) -> Result<Vec<candid::Principal>, IspError> {
+        let response_blob = self
+            .agent
+            .query(&self.canister_id, "getAdmins")
+            .with_arg(Encode!()?)
+            .call()
+            .await?;
+        Ok(Decode!(&response_blob, Vec<candid::Principal>)?)
+    }
+
+    /// Store files from folder_path, return Vec<(file_name, file_key)>
+    ///
+    /// If http open,url format: icsp_canister_id.raw.ic0.app/ic/file_key
+    pub async fn store_files(
+        &self,
+        folder_path: &str,
+        is_http_open: bool,
+    ) -> Result<Vec<(String, String)>, IspError> {
+        let mut file_paths = Vec::new();
+        let mut batch = BatchProgress::default();
+        for entry in fs::read_dir(folder_path)? {
+            let entry = entry?;
+            batch.total_bytes += entry.metadata()?.len();
+            file_paths.push(entry.path());
+        }
+        batch.total_files = file_paths.len();
+
+        let mut ans: Vec<(String, String)> = Vec::new();
+        for file_path in file_paths {
+            ans.push(
+                self.store_path(&file_path, is_http_open, None, None, Some(&mut batch))
+                    .await?
+                    .names(),
+            );
+        }
+        Ok(ans)
+    }
+
+    /// Store every file under dir_path and its subdirectories, return the manifest of the stored files
+    ///
+    /// Files are stored in path order, symbolic links are not followed. The manifest maps the
+    /// path of every file relative to dir_path, e.g. `css/site.min.css`, to its key, type and size.
+    ///
+    /// If http open,url format: icsp_canister_id.raw.ic0.app/ic/file_key
+    ///
+    /// # Examples
+    ///
+    /// ``` no_run
+    /// use isp_sdk::{IcspHandle, IspError};
+    ///
+    /// pub async fn store_site(icsp: &IcspHandle) -> Result<(), IspError> {
+    ///     let manifest = icsp.store_dir("dist", true).await?;
+    ///     for (path, entry) in &manifest {
+    ///         println!("{} -> {} ({}, {} bytes)", path, entry.file_key, entry.file_type, entry.size);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn store_dir(
+        &self,
+        dir_path: &str,
+        is_http_open: bool,
+    ) -> Result<Manifest, IspError> {
+        self.check_http_open(is_http_open)?;
+        let (files, mut batch) = walk_dir(Path::new(dir_path))?;
+        let mut manifest = Manifest::new();
+        for (relative_path, file_path) in files {
+            let stored = self
+                .store_path(&file_path, is_http_open, None, None, Some(&mut batch))
+                .await?;
+            manifest.insert(relative_path, stored.into_entry());
+        }
+        Ok(manifest)
+    }
+
+    /// Publish every file under dir_path and its subdirectories as an http open file keyed by its relative path
+    ///
+    /// `dist/assets/app.js` is served at icsp_canister_id.raw.ic0.app/ic/assets/app.js.
+    /// A file the icsp already has with the same sha256 and type is not uploaded again,
+    /// a changed file replaces the stored one like [`IcspHandle::replace_file`]. Files are
+    /// stored uncompressed and unencrypted whatever the settings of the handle, so that the
+    /// gateway serves them as they are.
+    ///
+    /// # Examples
+    ///
+    /// ``` no_run
+    /// use isp_sdk::{IcspHandle, IspError, Network};
+    ///
+    /// pub async fn deploy(icsp: &IcspHandle) -> Result<(), IspError> {
+    ///     let deployment = icsp.deploy_dir("dist").await?;
+    ///     println!(
+    ///         "uploaded {} files, {} unchanged",
+    ///         deployment.uploaded.len(),
+    ///         deployment.unchanged.len()
+    ///     );
+    ///     println!("site:{:?}", deployment.url(&Network::Mainnet, "index.html"));
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn deploy_dir(&self, dir_path: &str) -> Result<Deployment, IspError> {
+        let site = IcspHandle {
+            content_addressed: false,
+            encryption_key: None,
+            compression: None,
+            ..self.clone()
+        };
+        let (files, mut batch) = walk_dir(Path::new(dir_path))?;
+        let mut deployment = Deployment::new(self.canister_id);
+        for (relative_path, file_path) in files {
+            let local = open_local(&file_path, None).await?;
+            let file_info = site.raw_file_info(&relative_path).await?;
+            let entry = match file_info {
+                Some(file_info) if site.has_local(&file_info, &local, true) => {
+                    batch.files_done += 1;
+                    batch.bytes_done += local.size;
+                    deployment.unchanged.push(relative_path.clone());
+                    ManifestEntry {
+                        file_key: relative_path.clone(),
+                        file_type: local.file_type,
+                        size: local.size,
+                        sha256: local.sha256,
+                    }
+                }
+                // a changed file is staged first so a failed upload keeps the old one
+                Some(_) => {
+                    let replacement = Replacement::Path {
+                        file_path,
+                        file_type: local.file_type.clone(),
+                    };
+                    let replaced =
+                        site.replace_with(&relative_path, &local.sha256, true, replacement);
+                    deployment.staging_keys.extend(replaced.await?);
+                    let chunks = total_index(local.size);
+                    site.tracker(&relative_path, local.size, chunks, Some(&mut batch))
+                        .file_done();
+                    deployment.uploaded.push(relative_path.clone());
+                    ManifestEntry {
+                        file_key: relative_path.clone(),
+                        file_type: local.file_type,
+                        size: local.size,
+                        sha256: local.sha256,
+                    }
+                }
+                None => {
+                    let file_key = Some(relative_path.clone());
+                    let stored = site
+                        .store_local(local, true, file_key, Some(&mut batch))
+                        .await?;
+                    deployment.uploaded.push(relative_path.clone());
+                    stored.into_entry()
+                }
+            };
+            deployment.manifest.insert(relative_path, entry);
+        }
+        Ok(deployment)
+    }
+
+    /// Make the icsp mirror dir_path and its subdirectories, return what changed
+    ///
+    /// The manifest at manifest_path, a json file kept by earlier syncs, holds the sha256 of
+    /// every file synced. A local file is uploaded, keyed by its relative path, when the icsp
+    /// does not have it or its content or type changed, a changed file replaces the stored one
+    /// like [`IcspHandle::replace_file`]. A file removed locally is deleted from the icsp only
+    /// with `delete_removed`. With `dry_run` nothing is uploaded, deleted or written and the
+    /// report shows what would change.
+    ///
+    /// # Examples
+    ///
+    /// ``` no_run
+    /// use isp_sdk::icsp::SyncOptions;
+    /// use isp_sdk::{IcspHandle, IspError};
+    ///
+    /// pub async fn sync(icsp: &IcspHandle) -> Result<(), IspError> {
+    ///     let mut options = SyncOptions {
+    ///         delete_removed: true,
+    ///         dry_run: true,
+    ///         ..SyncOptions::default()
+    ///     };
+    ///     let report = icsp.sync_dir("shared", "shared.manifest.json", options).await?;
+    ///     println!("{}", report);
+    ///     if !report.is_in_sync() {
+    ///         options.dry_run = false;
+    ///         icsp.sync_dir("shared", "shared.manifest.json", options).await?;
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn sync_dir(
+        &self,
+        dir_path: &str,
+        manifest_path: &str,
+        options: SyncOptions,
+    ) -> Result<SyncReport, IspError> {
+        self.check_http_open(options.is_http_open)?;
+        let manifest_path = Path::new(manifest_path);
+        let mut old_manifest = read_manifest(manifest_path)?;
+        let remote_keys: HashSet<String> = self.get_all_ic_file_key().await?.into_iter().collect();
+        let (files, _) = walk_dir(Path::new(dir_path))?;
+        let mut report = SyncReport {
+            delete_removed: options.delete_removed,
+            dry_run: options.dry_run,
+            ..SyncReport::default()
+        };
+
+        // compare every local file with the manifest and the icsp
+        let mut uploads: Vec<(String, PathBuf, ManifestEntry, bool)> = Vec::new();
+        for (relative_path, file_path) in files {
+            let local = open_local(&file_path, None).await?;
+            let old_entry = old_manifest.remove(&relative_path);
+            let file_key = old_entry
+                .as_ref()
+                .map_or_else(|| relative_path.clone(), |entry| entry.file_key.clone());
+            let entry = ManifestEntry {
+                file_key: file_key.clone(),
+                file_type: local.file_type.clone(),
+                size: local.size,
+                sha256: local.sha256.clone(),
+            };
+            let remote = remote_keys.contains(&file_key);
+            let unchanged = remote
+                && match old_entry {
+                    Some(old_entry) if old_entry == entry => true,
+                    _ => match self.raw_file_info(&file_key).await? {
+                        Some(file_info) => self.has_local(&file_info, &local, options.is_http_open),
+                        None => false,
+                    },
+                };
+            if unchanged {
+                report.unchanged.push(relative_path.clone());
+                report.manifest.insert(relative_path, entry);
+            } else {
+                match remote {
+                    true => report.changed.push(relative_path.clone()),
+                    false => report.added.push(relative_path.clone()),
+                }
+                uploads.push((relative_path, file_path, entry, remote));
+            }
+        }
+
+        // files left in the old manifest were removed locally
+        for (relative_path, entry) in old_manifest {
+            if !remote_keys.contains(&entry.file_key) {
+                continue;
+            }
+            report.removed.push(relative_path.clone());
+            if options.delete_removed {
+                if !options.dry_run {
+                    self.delete_file(&entry.file_key).await?;
+                }
+            } else {
+                report.manifest.insert(relative_path, entry);
+            }
+        }
+
+        let mut batch = BatchProgress {
+            total_files: uploads.len(),
+            total_bytes: uploads.iter().map(|(_, _, entry, _)| entry.size).sum(),
+            ..BatchProgress::default()
+        };
+        for (relative_path, file_path, entry, remote) in uploads {
+            if options.dry_run {
+                report.manifest.insert(relative_path, entry);
+                continue;
+            }
+            let file_type = Some(entry.file_type.as_str());
+            if remote {
+                // a changed file is staged first so a failed upload keeps the old one
+                let replacement = Replacement::Path {
+                    file_path,
+                    file_type: entry.file_type.clone(),
+                };
+                let replaced = self.replace_with(
+                    &entry.file_key,
+                    &entry.sha256,
+                    options.is_http_open,
+                    replacement,
+                );
+                report.staging_keys.extend(replaced.await?);
+                let chunks = total_index(entry.size);
+                self.tracker(&entry.file_key, entry.size, chunks, Some(&mut batch))
+                    .file_done();
+            } else {
+                let file_key = Some(entry.file_key.clone());
+                let stored = self.store_path(
+                    &file_path,
+                    options.is_http_open,
+                    file_key,
+                    file_type,
+                    Some(&mut batch),
+                );
+                stored.await?;
+            }
+            // keep the files synced so far should a later upload fail
+            report.manifest.insert(relative_path, entry);
+            write_manifest(manifest_path, &report.manifest)?;
+        }
+        if !options.dry_run {
+            write_manifest(manifest_path, &report.manifest)?;
+        }
+        Ok(report)
+    }
+
+    /// Store a file from file_path, return (file_name, file_key)
+    ///
+    /// If http open,url format: icsp_canister_id.raw.ic0.app/ic/file_key
+    pub async fn store_file(
+        &self,
+        file_path_str: &str,
+        is_http_open: bool,
+    ) -> Result<(String, String), IspError> {
+        let file_path = Path::new(file_path_str);
+        let stored = self.store_path(file_path, is_http_open, None, None, None);
+        Ok(stored.await?.names())
+    }
+
+    /// Store a file from file_path as file_type instead of the detected type, return (file_name, file_key)
+    ///
+    /// A uuid or, if the handle is content addressed, the sha256 of the file is used when file_key is None.
+    ///
+    /// If http open,url format: icsp_canister_id.raw.ic0.app/ic/file_key
+    ///
+    /// # Examples
+    ///
+    /// ``` no_run
+    /// use isp_sdk::{IcspHandle, IspError};
+    ///
+    /// pub async fn store_manifest(icsp: &IcspHandle) -> Result<(), IspError> {
+    ///     let (_, file_key) = icsp
+    ///         .store_file_with_type("site/manifest", "application/manifest+json", true, None)
+    ///         .await?;
+    ///     println!("file_key:{:?}", file_key);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn store_file_with_type(
+        &self,
+        file_path_str: &str,
+        file_type: &str,
+        is_http_open: bool,
+        file_key: Option<String>,
+    ) -> Result<(String, String), IspError> {
+        let file_path = Path::new(file_path_str);
+        let stored = self.store_path(file_path, is_http_open, file_key, Some(file_type), None);
+        Ok(stored.await?.names())
+    }
+
+    /// Store file with given key, return (file_name, file_key)
+    ///
+    /// If http open,url format: icsp_canister_id.raw.ic0.app/ic/file_key
+    pub async fn store_file_by_key(
+        &self,
+        file_path_str: &str,
+        is_http_open: bool,
+        file_key: String,
+    ) -> Result<(String, String), IspError> {
+        let file_path = Path::new(file_path_str);
+        let stored = self.store_path(file_path, is_http_open, Some(file_key), None, None);
+        Ok(stored.await?.names())
+    }
+
+    /// Store total_size bytes read from reader as a file of file_type, e.g. "video/mp4"
+    ///
+    /// The reader is read one chunk at a time, at most upload_concurrency + 1 chunks are held in memory.
+    /// The reader can not be read twice, so unlike the other store methods no sha256 is recorded
+    /// for `get_file` to verify and the file is not compressed.
+    ///
+    /// If http open,url format: icsp_canister_id.raw.ic0.app/ic/file_key
+    ///
+    /// # Examples
+    ///
+    /// ``` no_run
+    /// use isp_sdk::{IcspHandle, IspError};
+    ///
+    /// pub async fn store_archive(icsp: &IcspHandle) -> Result<(), IspError> {
+    ///     let file = tokio::fs::File::open("backups/archive.tar").await?;
+    ///     let total_size = file.metadata().await?.len();
+    ///     icsp.store_reader(file, total_size, "application/x-tar", false, "archive".to_string())
+    ///         .await
+    /// }
+    /// ```
+    pub async fn store_reader<R: AsyncRead + Unpin>(
+        &self,
+        reader: R,
+        total_size: u64,
+        file_type: &str,
+        is_http_open: bool,
+        file_key: String,
+    ) -> Result<(), IspError> {
+        let header = FileHeader {
+            file_key,
+            file_type: self.new_file_type(file_type, None, None),
+            total_size,
+            is_http_open,
+            encryption_key: self.encryption_key.clone(),
+        };
+        self.store_header(reader, header, None).await
+    }
+
+    /// Continue an interrupted upload of file_key, reading the file again from reader
+    ///
+    /// Only the chunks the icsp has not received are read from reader and stored, the reader must
+    /// return the same bytes as the source of the interrupted upload.
+    /// Return [`IspError::Incomplete`] if the icsp still misses chunks at the end.
+    ///
+    /// # Examples
+    ///
+    /// ``` no_run
+    /// use isp_sdk::{IcspHandle, IspError};
+    ///
+    /// pub async fn store_archive(icsp: &IcspHandle) -> Result<(), IspError> {
+    ///     let file_key = "nightly-archive".to_string();
+    ///     let file = tokio::fs::File::open("backups/archive.tar").await?;
+    ///     let total_size = file.metadata().await?.len();
+    ///     let stored = icsp
+    ///         .store_reader(file, total_size, "application/x-tar", false, file_key.clone())
+    ///         .await;
+    ///     if let Err(IspError::Upload { .. }) = stored {
+    ///         let file = tokio::fs::File::open("backups/archive.tar").await?;
+    ///         icsp.resume_upload(&file_key, file).await?;
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn resume_upload<R: AsyncRead + Unpin>(
+        &self,
+        file_key: &str,
+        reader: R,
+    ) -> Result<(), IspError> {
+        let file_info = self
+            .raw_file_info(file_key)
+            .await?
+            .ok_or_else(|| IspError::FileNotFound(file_key.to_string()))?;
+        self.resume_file(reader, file_key, file_info, None).await
+    }
+
+    // Store the chunks of file_info missing from the icsp and verify it has every chunk
+    async fn resume_file<R: AsyncRead + Unpin>(
+        &self,
+        reader: R,
+        file_key: &str,
+        file_info: FileBufExt,
+        mut batch: Option<&mut BatchProgress>,
+    ) -> Result<(), IspError> {
+        let (_, meta) = decode_file_type(&file_info.file_type);
+        let encryption_key = self.encryption_key_of(file_key, &meta)?.cloned();
+        match meta.compression {
+            None => {
+                self.resume_stored(reader, file_key, file_info, encryption_key, batch)
+                    .await
+            }
+            // compression is deterministic, compressing the source again gives the stored bytes
+            Some(compression) => {
+                let (file, original_size, size) = compression.compress_reader(reader).await?;
+                adjust_batch(&mut batch, original_size, size);
+                self.resume_stored(file, file_key, file_info, encryption_key, batch)
+                    .await
+            }
+        }
+    }
+
+    // Store the chunks of the stored bytes missing from the icsp and verify it has every chunk
+    async fn resume_stored<R: AsyncRead + Unpin>(
+        &self,
+        reader: R,
+        file_key: &str,
+        file_info: FileBufExt,
+        encryption_key: Option<EncryptionKey>,
+        batch: Option<&mut BatchProgress>,
+    ) -> Result<(), IspError> {
+        let total_size = content_size(
+            file_info.total_size,
+            &file_info.total_index,
+            encryption_key.is_some(),
+        );
+        let header = FileHeader {
+            file_key: file_key.to_string(),
+            file_type: file_info.file_type,
+            total_size,
+            is_http_open: file_info.is_http_open,
+            encryption_key,
+        };
+        let (indices, wrote): (Vec<usize>, Vec<usize>) = (0..header.total_index())
+            .partition(|&index| !file_info.wrote_page.get(index).copied().unwrap_or(false));
+        let mut tracker = self.tracker(file_key, header.total_size, header.total_index(), batch);
+        let wrote_bytes = wrote.iter().map(|&index| header.chunk_size(index)).sum();
+        tracker.skip(wrote_bytes, wrote.len());
+        self.store_chunks(read_chunks(reader, header, indices), &mut tracker)
+            .await?;
+        self.verify_stored(file_key, None).await
+    }
+
+    fn bucket(&self, bucket_canister_id: candid::Principal) -> BucketHandle {
+        BucketHandle::new(self.agent.clone(), bucket_canister_id)
+    }
+
+    // Check the icsp has every chunk of the file and, if given, recorded sha256 for it
+    async fn verify_stored(&self, file_key: &str, sha256: Option<&str>) -> Result<(), IspError> {
+        let file_info = self
+            .raw_file_info(file_key)
+            .await?
+            .ok_or_else(|| IspError::FileNotFound(file_key.to_string()))?;
+        if file_info.received != file_info.total_index {
+            return Err(IspError::Incomplete {
+                file_key: file_key.to_string(),
+                received: file_info.received,
+                total_index: file_info.total_index,
+            });
+        }
+        let Some(expected) = sha256 else {
+            return Ok(());
+        };
+        let (_, meta) = decode_file_type(&file_info.file_type);
+        let expected = self.recorded_digest(expected);
+        if meta.digest() != Some(expected.as_str()) {
+            return Err(IspError::Integrity {
+                file_key: file_key.to_string(),
+                actual: meta.digest().unwrap_or_default().to_string(),
+                expected,
+            });
+        }
+        Ok(())
+    }
+
+    // Replace file_key with replacement, which has digest sha256, return the staging key if the
+    // staging copy could not be deleted
+    //
+    // The new file is stored and verified under a staging key first, the old file is untouched
+    // if that fails. Then the chunks of the old file are backed up as stored to a temporary
+    // file, the old file deleted and the new file stored under file_key, the backup is stored
+    // again with its original metadata if that fails. An old file that can not be read back,
+    // e.g. incomplete or missing a chunk, is replaced without a backup.
+    async fn replace_with(
+        &self,
+        file_key: &str,
+        sha256: &str,
+        is_http_open: bool,
+        replacement: Replacement<'_>,
+    ) -> Result<Option<String>, IspError> {
+        self.check_http_open(is_http_open)?;
+        let staging_key = format!("{}.staging-{}", file_key, Uuid::new_v4());
+        let staged = async {
+            self.store_replacement(&replacement, staging_key.clone(), false)
+                .await?;
+            self.verify_stored(&staging_key, Some(sha256)).await?;
+            self.swap(file_key, sha256, is_http_open, &replacement)
+                .await
+        };
+        let result = staged.await;
+        // the replace is done or failed whether or not the staging copy is deleted
+        let cleaned = self.delete_file(&staging_key).await;
+        result.map(|_| cleaned.err().map(|_| staging_key))
+    }
+
+    async fn store_replacement(
+        &self,
+        replacement: &Replacement<'_>,
+        file_key: String,
+        is_http_open: bool,
+    ) -> Result<(), IspError> {
+        let file_key = Some(file_key);
+        match replacement {
+            Replacement::Bytes { data, mime } => self
+                .store_bytes(data, mime, file_key, is_http_open)
+                .await
+                .map(drop),
+            Replacement::Path {
+                file_path,
+                file_type,
+            } => self
+                .store_path(file_path, is_http_open, file_key, Some(file_type), None)
+                .await
+                .map(drop),
+        }
+    }
+
+    // Store the new file under file_key in place of the old file, see replace_with
+    async fn swap(
+        &self,
+        file_key: &str,
+        sha256: &str,
+        is_http_open: bool,
+        replacement: &Replacement<'_>,
+    ) -> Result<(), IspError> {
+        let file_info = self.raw_file_info(file_key).await?;
+        let exists = file_info.is_some();
+        let backup = match file_info {
+            None => None,
+            Some(file_info) => self.back_up(file_key, file_info).await?,
+        };
+        let swapped = async {
+            if exists {
+                self.delete_file(file_key).await?;
+            }
+            self.store_replacement(replacement, file_key.to_string(), is_http_open)
+                .await?;
+            self.verify_stored(file_key, Some(sha256)).await
+        };
+        let Err(error) = swapped.await else {
+            return Ok(());
+        };
+
+        // roll back to the old file, the new file may be partly stored or not at all
+        let _ = self.delete_file(file_key).await;
+        if let Some(backup) = backup {
+            if let Err(restore_error) = self.restore(file_key, backup).await {
+                return Err(IspError::Restore {
+                    file_key: file_key.to_string(),
+                    error: Box::new(error),
+                    restore_error: Box::new(restore_error),
+                });
+            }
+        }
+        Err(error)
+    }
+
+    // The chunks of the file as stored, None if the bucket does not have every chunk
+    async fn back_up(
+        &self,
+        file_key: &str,
+        file_info: FileBufExt,
+    ) -> Result<Option<Backup>, IspError> {
+        if file_info.received < file_info.total_index {
+            return Ok(None);
+        }
+        let bucket = self.bucket(file_info.bucket_id);
+        let file = NamedTempFile::new()?;
+        let mut writer = tokio::fs::File::from_std(file.reopen()?);
+        let mut chunk_sizes = Vec::new();
+        let mut chunks = stream::iter(0..nat_to_usize(&file_info.total_index))
+            .map(|index| bucket.get(file_key, index))
+            .buffered(self.download_concurrency);
+        while let Some(chunk) = chunks.next().await {
+            let Some((data, _)) = chunk? else {
+                return Ok(None);
+            };
+            writer.write_all(&data).await?;
+            chunk_sizes.push(data.len());
+        }
+        writer.flush().await?;
+        Ok(Some(Backup {
+            file,
+            chunk_sizes,
+            file_info,
+        }))
+    }
+
+    // Store the backed up chunks again under file_key as they were stored
+    async fn restore(&self, file_key: &str, backup: Backup) -> Result<(), IspError> {
+        let Backup {
+            file,
+            chunk_sizes,
+            file_info,
+        } = backup;
+        let reader = tokio::fs::File::from_std(file.reopen()?);
+        let FileBufExt {
+            total_index,
+            file_type,
+            is_http_open,
+            total_size,
+            ..
+        } = file_info;
+        let mut tracker = self.tracker(file_key, total_size, chunk_sizes.len(), None);
+        let key = file_key.to_string();
+        let chunks = stream::try_unfold(
+            (reader, chunk_sizes.into_iter().enumerate()),
+            move |(mut reader, mut sizes)| {
+                let (key, total_index, file_type) =
+                    (key.clone(), total_index.clone(), file_type.clone());
+                async move {
+                    let Some((index, size)) = sizes.next() else {
+                        return Ok(None);
+                    };
+                    let mut value = vec![0; size];
+                    reader.read_exact(&mut value).await?;
+                    let chunk = StoreArgs {
+                        key,
+                        value,
+                        total_index,
+                        file_type,
+                        total_size,
+                        is_http_open,
+                        index: Nat::from(index),
+                    };
+                    Ok(Some(((index, chunk), (reader, sizes))))
+                }
+            },
+        );
+        self.store_chunks(chunks, &mut tracker).await
+    }
+
+    /// Delete file by file_key
+    pub async fn delete_file(&self, file_key: &str) -> Result<(), IspError> {
+        self.agent
+            .update(&self.canister_id, "delete")
+            .with_arg(Encode!(&file_key.to_string())?)
+            .call_and_wait()
+            .await?;
+        Ok(())
+    }
+
+    /// Store str data, return file_key
+    ///
+    /// If http open,url format: icsp_canister_id.raw.ic0.app/ic/file_key
+    pub async fn store_str(&self, data: &str, is_http_open: bool) -> Result<String, IspError> {
+        self.store_bytes(data.as_bytes(), "text/plain", None, is_http_open)
+            .await
+    }
+
+    /// Replace the value str corresponding to the key, see [`IcspHandle::replace_bytes`]
+    pub async fn replace_str(
+        &self,
+        file_key: &str,
+        data: &str,
+        is_http_open: bool,
+    ) -> Result<Option<String>, IspError> {
+        self.replace_bytes(file_key, data.as_bytes(), "text/plain", is_http_open)
+            .await
+    }
+
+    /// Replace the file of file_key with data of mime type, keeping the old file if it fails
+    ///
+    /// data is first stored and verified under a staging key, so a failed upload leaves the old
+    /// file untouched. Only then are the stored chunks of the old file backed up to a temporary
+    /// local file, the old file deleted and data stored under file_key. If that fails the backup
+    /// is stored again as it was and the error is returned, or [`IspError::Restore`] if the
+    /// backup could not be stored either. An old file that can not be read back, e.g. one that
+    /// is incomplete, is replaced without a backup. The icsp can not rename a file, so readers
+    /// may miss the file while it is swapped.
+    ///
+    /// Return the key of the staging copy if the file was replaced but the copy could not be
+    /// deleted, delete it later with [`IcspHandle::delete_file`].
+    ///
+    /// # Examples
+    ///
+    /// ``` no_run
+    /// use isp_sdk::{IcspHandle, IspError};
+    ///
+    /// pub async fn update_config(icsp: &IcspHandle, config: &[u8]) -> Result<(), IspError> {
+    ///     let staging_key = icsp
+    ///         .replace_bytes("config.json", config, "application/json", true)
+    ///         .await?;
+    ///     if let Some(staging_key) = staging_key {
+    ///         icsp.delete_file(&staging_key).await?;
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn replace_bytes(
+        &self,
+        file_key: &str,
+        data: &[u8],
+        mime: &str,
+        is_http_open: bool,
+    ) -> Result<Option<String>, IspError> {
+        let sha256 = sha256_hex(data);
+        let replacement = Replacement::Bytes { data, mime };
+        self.replace_with(file_key, &sha256, is_http_open, replacement)
+            .await
+    }
+
+    /// Replace the file of file_key with the file at file_path, see [`IcspHandle::replace_bytes`]
+    pub async fn replace_file(
+        &self,
+        file_key: &str,
+        file_path_str: &str,
+        is_http_open: bool,
+    ) -> Result<Option<String>, IspError> {
+        let file_path = Path::new(file_path_str);
+        let LocalFile {
+            file_type, sha256, ..
+        } = open_local(file_path, None).await?;
+        let replacement = Replacement::Path {
+            file_path: file_path.to_path_buf(),
+            file_type,
+        };
+        self.replace_with(file_key, &sha256, is_http_open, replacement)
+            .await
+    }
+
+    /// Store data of any size as a file of mime type, e.g. "image/png", return file_key
+    ///
+    /// A uuid or, if the handle is content addressed, the sha256 of data is used when file_key is None.
+    ///
+    /// If http open,url format: icsp_canister_id.raw.ic0.app/ic/file_key
+    ///
+    /// # Examples
+    ///
+    /// ``` no_run
+    /// use isp_sdk::{IcspHandle, IspError};
+    ///
+    /// pub async fn store_json(icsp: &IcspHandle) -> Result<(), IspError> {
+    ///     let metadata = br#"{"name":"isp","image":"ipfs://bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi"}"#;
+    ///     let file_key = icsp
+    ///         .store_bytes(metadata, "application/json", Some("nft/1.json".to_string()), true)
+    ///         .await?;
+    ///     println!("file_key:{:?}", file_key);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn store_bytes(
+        &self,
+        data: &[u8],
+        mime: &str,
+        file_key: Option<String>,
+        is_http_open: bool,
+    ) -> Result<String, IspError> {
+        self.check_http_open(is_http_open)?;
+        let sha256 = sha256_hex(data);
+        let file_key = match file_key {
+            Some(file_key) => file_key,
+            None if self.content_addressed => {
+                let file_key = self.recorded_digest(&sha256);
+                if let Some(file_info) = self.raw_file_info(&file_key).await? {
+                    self.resume_file(data, &file_key, file_info, None).await?;
+                    return Ok(file_key);
+                }
+                file_key
+            }
+            None => Uuid::new_v4().to_string(),
+        };
+        let compressed;
+        let data = match self.compression {
+            None => data,
+            Some(compression) => {
+                compressed = compression.compress(data)?;
+                &compressed
+            }
+        };
+        let header = FileHeader {
+            file_key: file_key.clone(),
+            file_type: self.new_file_type(mime, Some(&sha256), self.compression),
+            total_size: data.len() as u64,
+            is_http_open,
+            encryption_key: self.encryption_key.clone(),
+        };
+        self.store_header(data, header, None).await?;
+        Ok(file_key)
+    }
+
+    /// Get file from icsp, return (data, file_type)
+    ///
+    /// Return [`IspError::Integrity`] if data does not match the digest recorded when it was stored.
+    pub async fn get_file(&self, file_key: &str) -> Result<(Vec<u8>, String), IspError> {
+        let mut payload: Vec<u8> = Vec::new();
+        let file_type = self.get_file_to_writer(file_key, &mut payload).await?;
+        Ok((payload, file_type))
+    }
+
+    /// Get file from icsp and write it to writer in order, return file_type
+    ///
+    /// Up to download_concurrency chunks are held in memory. If a sha256, or the keyed digest of an
+    /// encrypted file, was recorded when the file was stored, the written bytes are verified
+    /// against it at the end and [`IspError::Integrity`] is returned on mismatch.
+    ///
+    /// # Examples
+    ///
+    /// ``` no_run
+    /// use isp_sdk::{IcspHandle, IspError};
+    ///
+    /// pub async fn save_video(icsp: &IcspHandle, file_key: &str) -> Result<(), IspError> {
+    ///     let mut file = tokio::fs::File::create("downloads/video.mp4").await?;
+    ///     let file_type = icsp.get_file_to_writer(file_key, &mut file).await?;
+    ///     println!("file_type:{:?}", file_type);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn get_file_to_writer<W: AsyncWrite + Unpin>(
+        &self,
+        file_key: &str,
+        writer: &mut W,
+    ) -> Result<String, IspError> {
+        let (bucket_canister_id, total_index) = self.locate_file(file_key).await?;
+        let total_size = match self.progress {
+            Some(_) => self.raw_file_info(file_key).await?.map_or(0, |file_info| {
+                let (_, meta) = decode_file_type(&file_info.file_type);
+                let encrypted = meta.key_fingerprint.is_some();
+                content_size(file_info.total_size, &file_info.total_index, encrypted)
+            }),
+            None => 0,
+        };
+        let mut tracker = self.tracker(file_key, total_size, total_index, None);
+
+        let mut chunks = stream::iter(0..total_index)
+            .map(|index| self.get_chunk(bucket_canister_id, file_key, index))
+            .buffered(self.download_concurrency);
+        let mut file_type = "".to_string();
+        let mut hasher = Sha256::new();
+        let mut decoder: Option<ChunkDecoder> = None;
+        while let Some(chunk) = chunks.next().await {
+            let (data, chunk_file_type) = chunk?;
+            tracker.chunk_done(data.len() as u64);
+            let decoder = match &mut decoder {
+                Some(decoder) => decoder,
+                None => {
+                    let (_, meta) = decode_file_type(&chunk_file_type);
+                    decoder.insert(ChunkDecoder::new(meta.compression)?)
+                }
+            };
+            let data = decoder.decode(data)?;
+            writer.write_all(&data).await?;
+            hasher.update(&data);
+            file_type = chunk_file_type;
+        }
+        if let Some(decoder) = decoder {
+            let data = decoder.finish()?;
+            writer.write_all(&data).await?;
+            hasher.update(&data);
+        }
+        writer.flush().await?;
+
+        let (file_type, meta) = decode_file_type(&file_type);
+        if let Some(expected) = meta.digest() {
+            let sha256 = hex::encode(hasher.finalize());
+            let actual = match (&meta.mac, self.encryption_key_of(file_key, &meta)?) {
+                (Some(_), Some(key)) => key.digest_mac(&sha256),
+                _ => sha256,
+            };
+            if actual != expected {
+                return Err(IspError::Integrity {
+                    file_key: file_key.to_string(),
+                    expected: expected.to_string(),
+                    actual,
+                });
+            }
+        }
+        Ok(file_type)
+    }
+
+    /// Read len bytes of the file starting at offset, fewer if the file ends before
+    ///
+    /// Only the chunks overlapping the range are fetched, this relies on the file being stored in
+    /// chunks of the same size as the store methods of this crate use.
+    /// Return [`IspError::Compressed`] if the file is stored compressed.
+    ///
+    /// # Examples
+    ///
+    /// ``` no_run
+    /// use isp_sdk::{IcspHandle, IspError};
+    ///
+    /// pub async fn read_header(icsp: &IcspHandle, file_key: &str) -> Result<(), IspError> {
+    ///     let header = icsp.read_range(file_key, 0, 512).await?;
+    ///     println!("header:{:?}", header);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn read_range(
+        &self,
+        file_key: &str,
+        offset: u64,
+        len: usize,
+    ) -> Result<Vec<u8>, IspError> {
+        if len == 0 {
+            return Ok(Vec::new());
+        }
+        let (bucket_canister_id, total_index) = self.locate_file(file_key).await?;
+        let (indices, skip) = range_chunks(offset, len, total_index);
+
+        let mut chunks = stream::iter(indices)
+            .map(|index| self.get_chunk(bucket_canister_id, file_key, index as usize))
+            .buffered(self.download_concurrency);
+        let mut data: Vec<u8> = Vec::new();
+        while let Some(chunk) = chunks.next().await {
+            let (mut chunk, file_type) = chunk?;
+            if decode_file_type(&file_type).1.compression.is_some() {
+                return Err(IspError::Compressed(file_key.to_string()));
+            }
+            data.append(&mut chunk);
+        }
+        Ok(slice_range(data, skip, len))
+    }
+
+    /// Open the file for reading and seeking, its chunks are fetched when they are read
+    pub async fn open_file(&self, file_key: &str) -> Result<RemoteFile, IspError> {
+        let file_info = self
+            .raw_file_info(file_key)
+            .await?
+            .ok_or_else(|| IspError::FileNotFound(file_key.to_string()))?;
+        let (_, meta) = decode_file_type(&file_info.file_type);
+        if meta.compression.is_some() {
+            return Err(IspError::Compressed(file_key.to_string()));
+        }
+        let encrypted = meta.key_fingerprint.is_some();
+        Ok(RemoteFile::new(
+            self.clone(),
+            file_key.to_string(),
+            file_info.bucket_id,
+            content_size(file_info.total_size, &file_info.total_index, encrypted),
+        ))
+    }
+
+    /// Get file from icsp and save it at file_path, return file_type
+    ///
+    /// The file is downloaded to a temporary file next to file_path and only moved to file_path
+    /// once it is complete and verified, file_path is left as it was on any error.
+    pub async fn get_file_to_path(
+        &self,
+        file_key: &str,
+        file_path_str: &str,
+    ) -> Result<String, IspError> {
+        let file_path = Path::new(file_path_str);
+        let dir = match file_path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let download = NamedTempFile::new_in(dir)?;
+        let mut writer = tokio::fs::File::from_std(download.reopen()?);
+        let file_type = self.get_file_to_writer(file_key, &mut writer).await?;
+        writer.flush().await?;
+        download.persist(file_path).map_err(|error| error.error)?;
+        Ok(file_type)
+    }
+
+    /// Add admin of icsp
+    pub async fn add_icsp_admin(&self, new_admin: candid::Principal) -> Result<(), IspError> {
+        self.agent
+            .update(&self.canister_id, "addAdmin")
+            .with_arg(Encode!(&new_admin)?)
+            .call_and_wait()
+            .await?;
+        Ok(())
+    }
+
+    /// Delete admin of icsp
+    pub async fn delete_icsp_admin(&self, old_admin: candid::Principal) -> Result<(), IspError> {
+        self.agent
+            .update(&self.canister_id, "deleteAdmin")
+            .with_arg(Encode!(&old_admin)?)
+            .call_and_wait()
+            .await?;
+        Ok(())
+    }
+
+    /// Top up every bucket some Cycles by using icsp's Cycles
+    pub async fn top_up_bucket(&self, amount: u64) -> Result<(), IspError> {
+        self.agent
+            .update(&self.canister_id, "topUpBucket")
+            .with_arg(Encode!(&Nat::from(amount))?)
+            .call_and_wait()
+            .await?;
+        Ok(())
+    }
+
+    /// Get ICSP's WASM version
+    pub async fn get_version(&self) -> Result<String, IspError> {
+        let response_blob = self
+            .agent
+            .query(&self.canister_id, "getVersion")
+            .with_arg(Encode!()?)
+            .call()
+            .await?;
+        Ok(Decode!(&response_blob, String)?)
+    }
+
+    /// Query the number of ic files stored in icsp
+    pub async fn get_ic_file_numbers(&self) -> Result<Option<Nat>, IspError> {
+        let response_blob = self
+            .agent
+            .query(&self.canister_id, "getIcFileNums")
+            .with_arg(Encode!()?)
+            .call()
+            .await?;
+        Ok(Decode!(&response_blob, Option<Nat>)?)
+    }
+
+    /// Slice all files by page_number and return the information of file_info at page_index
+    ///
+    /// The file_types are stripped of the sdk metadata as by [`IcspHandle::get_file_info`].
+    pub async fn get_field_file_infos(
+        &self,
+        page_number: u64,
+        page_index: u64,
+    ) -> Result<Vec<FileBufExt>, IspError> {
+        let response_blob = self
+            .agent
+            .query(&self.canister_id, "getFieldFileInfos")
+            .with_arg(Encode!(&Nat::from(page_number), &Nat::from(page_index))?)
+            .call()
+            .await?;
+        let file_infos = Decode!(&response_blob, Vec<FileBufExt>)?;
+        Ok(file_infos.into_iter().map(strip_file_info).collect())
+    }
+
+    /// Record a file stored on IPFS or Arweave
+    ///
+    /// url format: icsp_canister_id.raw.ic0.app/ipfs/file_key or icsp_canister_id.raw.ic0.app/ar/file_key
+    pub async fn record_file(&self, other_file: OtherFile) -> Result<(), IspError> {
+        self.agent
+            .update(&self.canister_id, "recordFile")
+            .with_arg(Encode!(&other_file)?)
+            .call_and_wait()
+            .await?;
+        Ok(())
+    }
+
+    /// Get the IPFS or Arweave file recorded with file_key
+    pub async fn get_other_file(
+        &self,
+        file_key: &str,
+        file_location: FileLocation,
+    ) -> Result<Option<OtherFile>, IspError> {
+        let response_blob = self
+            .agent
+            .query(&self.canister_id, "getOtherFile")
+            .with_arg(Encode!(&file_key, &file_location)?)
+            .call()
+            .await?;
+        Ok(Decode!(&response_blob, Option<OtherFile>)?)
+    }
+
+    /// Get all IPFS files 's key from user 's icsp
+    pub async fn get_all_ipfs_file_key(&self) -> Result<Vec<String>, IspError> {
+        let response_blob = self
+            .agent
+            .query(&self.canister_id, "getAllIpfsFileKey")
+            .with_arg(Encode!()?)
+            .call()
+            .await?;
+        Ok(Decode!(&response_blob, Vec<String>)?)
+    }
+
+    /// Get all Arweave files 's key from user 's icsp
+    pub async fn get_all_ar_file_key(&self) -> Result<Vec<String>, IspError> {
+        let response_blob = self
+            .agent
+            .query(&self.canister_id, "getAllArFileKey")
+            .with_arg(Encode!()?)
+            .call()
+            .await?;
+        Ok(Decode!(&response_blob, Vec<String>)?)
+    }
+
+    // Store the file at file_path with file_key or a generated key and file_type or the detected
+    // type, return (file_name, file_key)
+    async fn store_path(
+        &self,
+        file_path: &Path,
+        is_http_open: bool,
+        file_key: Option<String>,
+        file_type: Option<&str>,
+        batch: Option<&mut BatchProgress>,
+    ) -> Result<StoredPath, IspError> {
+        let local = open_local(file_path, file_type).await?;
+        self.store_local(local, is_http_open, file_key, batch).await
+    }
+
+    // Store a local file opened by open_local
+    async fn store_local(
+        &self,
+        local: LocalFile,
+        is_http_open: bool,
+        file_key: Option<String>,
+        mut batch: Option<&mut BatchProgress>,
+    ) -> Result<StoredPath, IspError> {
+        self.check_http_open(is_http_open)?;
+        let LocalFile {
+            file,
+            file_name,
+            file_type,
+            size: original_size,
+            sha256,
+        } = local;
+        let file_key = match file_key {
+            Some(file_key) => file_key,
+            None if self.content_addressed => {
+                let file_key = self.recorded_digest(&sha256);
+                if let Some(file_info) = self.raw_file_info(&file_key).await? {
+                    self.resume_file(file, &file_key, file_info, batch).await?;
+                    return Ok(StoredPath {
+                        file_name,
+                        file_key,
+                        file_type,
+                        size: original_size,
+                        sha256,
+                    });
+                }
+                file_key
+            }
+            None => Uuid::new_v4().to_string(),
+        };
+        let (file, total_size) = match self.compression {
+            None => (file, original_size),
+            Some(compression) => compression.compress_file(file.into_std().await).await?,
+        };
+        adjust_batch(&mut batch, original_size, total_size);
+        let header = FileHeader {
+            file_key: file_key.clone(),
+            file_type: self.new_file_type(&file_type, Some(&sha256), self.compression),
+            total_size,
+            is_http_open,
+            encryption_key: self.encryption_key.clone(),
+        };
+        self.store_header(file, header, batch).await?;
+        Ok(StoredPath {
+            file_name,
+            file_key,
+            file_type,
+            size: original_size,
+            sha256,
+        })
+    }
+
+    // Browsers can not decode a compressed file served by the gateway
+    fn check_http_open(&self, is_http_open: bool) -> Result<(), IspError> {
+        match self.compression.is_some() && is_http_open {
+            true => Err(IspError::CompressedHttpOpen),
+            false => Ok(()),
+        }
+    }
+
+    // Store every chunk of the file read from reader
+    async fn store_header<R: AsyncRead + Unpin>(
+        &self,
+        reader: R,
+        header: FileHeader,
+        batch: Option<&mut BatchProgress>,
+    ) -> Result<(), IspError> {
+        let total_index = header.total_index();
+        let mut tracker = self.tracker(&header.file_key, header.total_size, total_index, batch);
+        let chunks = read_chunks(reader, header, (0..total_index).collect());
+        self.store_chunks(chunks, &mut tracker).await
+    }
+
+    // file_type recording the metadata of a new file stored by this handle
+    fn new_file_type(
+        &self,
+        mime: &str,
+        sha256: Option<&str>,
+        compression: Option<Compression>,
+    ) -> String {
+        encode_file_type(mime, &self.new_file_meta(sha256, compression))
+    }
+
+    // Metadata of a new file with content sha256 stored by this handle
+    fn new_file_meta(&self, sha256: Option<&str>, compression: Option<Compression>) -> FileMeta {
+        let (sha256, mac) = match (&self.encryption_key, sha256) {
+            (Some(key), Some(sha256)) => (None, Some(key.digest_mac(sha256))),
+            (_, sha256) => (sha256.map(str::to_string), None),
+        };
+        FileMeta {
+            sha256,
+            mac,
+            key_fingerprint: self
+                .encryption_key
+                .as_ref()
+                .map(|key| key.fingerprint().to_string()),
+            compression,
+        }
+    }
+
+    // The digest this handle records for content with the hex sha256, the keyed one when encrypting
+    fn recorded_digest(&self, sha256: &str) -> String {
+        match &self.encryption_key {
+            Some(key) => key.digest_mac(sha256),
+            None => sha256.to_string(),
+        }
+    }
+
+    // Whether the icsp has the complete local file stored the way this handle stores it
+    fn has_local(&self, file_info: &FileBufExt, local: &LocalFile, is_http_open: bool) -> bool {
+        let (mime, meta) = decode_file_type(&file_info.file_type);
+        let stored_meta = self.new_file_meta(Some(&local.sha256), self.compression);
+        file_info.is_http_open == is_http_open
+            && file_info.received == file_info.total_index
+            && mime == local.file_type
+            && meta == stored_meta
+    }
+
+    // The key the chunks of a file with meta are encrypted with, None if it is not encrypted
+    fn encryption_key_of(
+        &self,
+        file_key: &str,
+        meta: &FileMeta,
+    ) -> Result<Option<&EncryptionKey>, IspError> {
+        match (&meta.key_fingerprint, &self.encryption_key) {
+            (None, _) => Ok(None),
+            (Some(_), None) => Err(IspError::EncryptionKeyMissing(file_key.to_string())),
+            (Some(fingerprint), Some(key)) if fingerprint == key.fingerprint() => Ok(Some(key)),
+            (Some(_), Some(_)) => Err(IspError::WrongEncryptionKey(file_key.to_string())),
+        }
+    }
+
+    fn tracker<'a>(
+        &'a self,
+        file_key: &str,
+        total_bytes: u64,
+        total_chunks: usize,
+        batch: Option<&'a mut BatchProgress>,
+    ) -> ProgressTracker<'a> {
+        let progress = self.progress.as_ref();
+        ProgressTracker::new(progress, file_key, total_bytes, total_chunks, batch)
+    }
+
+    // Store every (index, chunk) and report all the chunks that failed,
+    // an error of the chunk source stops the upload
+    async fn store_chunks(
+        &self,
+        chunks: impl Stream<Item = Result<(usize, StoreArgs), IspError>>,
+        tracker: &mut ProgressTracker<'_>,
+    ) -> Result<(), IspError> {
+        let mut chunks = Box::pin(chunks);
+        let Some((index, first)) = chunks.next().await.transpose()? else {
+            return Ok(());
+        };
+        let file_key = first.key.clone();
+        // the first chunk creates the file buffer, the others can be stored in any order
+        if let Err(error) = self.store_chunk(&first).await {
+            return Err(IspError::Upload {
+                file_key,
+                failures: vec![ChunkFailure { index, error }],
+            });
+        }
+        tracker.chunk_done(chunk_size(tracker.total_bytes(), index));
+        drop(first);
+        let mut stored = chunks
+            .map(|chunk| async move {
+                let (index, put) = chunk?;
+                Ok::<_, IspError>(
+                    self.store_chunk(&put)
+                        .await
+                        .map(|_| index)
+                        .map_err(|error| ChunkFailure { index, error }),
+                )
+            })
+            .buffer_unordered(self.upload_concurrency);
+        let mut failures: Vec<ChunkFailure> = Vec::new();
+        while let Some(result) = stored.next().await {
+            match result? {
+                Ok(index) => tracker.chunk_done(chunk_size(tracker.total_bytes(), index)),
+                Err(failure) => failures.push(failure),
+            }
+        }
+        if failures.is_empty() {
+            return Ok(());
+        }
+        failures.sort_by_key(|failure| failure.index);
+        Err(IspError::Upload { file_key, failures })
+    }
+
+    // Return the bucket storing file_key and the number of chunks of the file
+    async fn locate_file(&self, file_key: &str) -> Result<(candid::Principal, usize), IspError> {
+        let bucket_canister_id = self
+            .get_bucket_of_file(file_key)
+            .await?
+            .ok_or_else(|| IspError::BucketNotFound(file_key.to_string()))?;
+
+        let total_index = self
+            .bucket(bucket_canister_id)
+            .get_file_total_index(file_key)
+            .await?;
+        let total_index = nat_to_usize(&total_index);
+        Ok((bucket_canister_id, total_index))
+    }
+
+    async fn get_chunk(
+        &self,
+        bucket_canister_id: candid::Principal,
+        file_key: &str,
+        index: usize,
+    ) -> Result<(Vec<u8>, String), IspError> {
+        let (data, file_type) = self
+            .bucket(bucket_canister_id)
+            .get(file_key, index)
+            .await?
+            .ok_or_else(|| IspError::FileNotFound(file_key.to_string()))?;
+        let (_, meta) = decode_file_type(&file_type);
+        match self.encryption_key_of(file_key, &meta)? {
+            None => Ok((data, file_type)),
+            Some(key) => match key.decrypt_chunk(file_key, index, &data) {
+                Some(data) => Ok((data, file_type)),
+                None => Err(IspError::Decryption {
+                    file_key: file_key.to_string(),
+                    index,
+                }),
+            },
+        }
+    }
+
+    async fn store_chunk(&self, put: &StoreArgs) -> Result<(), IspError> {
+        self.agent
+            .update(&self.canister_id, "store")
+            .with_arg(Encode!(put)?)
+            .call_and_wait()
+            .await?;
+        Ok(())
+    }
+}
+
+fn build_handle(
+    pem_identity_path: &str,
+    icsp_canister_id_text: &str,
+) -> Result<IcspHandle, IspError> {
+    let canister_id = candid::Principal::from_text(icsp_canister_id_text)?;
+    Ok(IspClient::new(pem_identity_path)?.icsp(canister_id))
+}
+
+// Open the file at file_path for storing, detecting its type when file_type is None
+async fn open_local(file_path: &Path, file_type: Option<&str>) -> Result<LocalFile, IspError> {
+    let file_name = get_file_name(file_path)?;
+    let mut file = tokio::fs::File::open(file_path).await?;
+    let file_type = match file_type {
+        Some(file_type) => file_type.to_string(),
+        None => detect_file_type(file_path, &mut file).await?,
     };
-    let mut res = Vec::new();
-    for index in 0..slice_size {
-        if index == slice_size - 1 {
-            res.push(context[index * UPDATE_SIZE..context.len()].to_owned())
-        } else {
-            res.push(context[index * UPDATE_SIZE..(index + 1) * UPDATE_SIZE].to_owned())
+    let sha256 = sha256_reader(&mut file).await?;
+    file.rewind().await?;
+    let size = file.metadata().await?.len();
+    Ok(LocalFile {
+        file,
+        file_name,
+        file_type,
+        size,
+        sha256,
+    })
+}
+
+// Return the file_name of the file at path
+fn get_file_name(path: &Path) -> Result<String, IspError> {
+    Ok(path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .ok_or_else(|| IspError::InvalidPath(path.display().to_string()))?
+        .to_owned())
+}
+
+// A local file ready to be stored, read from its start
+struct LocalFile {
+    file: tokio::fs::File,
+    file_name: String,
+    file_type: String,
+    size: u64,
+    sha256: String,
+}
+
+// The new file of replace_with
+enum Replacement<'a> {
+    Bytes {
+        data: &'a [u8],
+        mime: &'a str,
+    },
+    Path {
+        file_path: PathBuf,
+        file_type: String,
+    },
+}
+
+// The chunks of a file as stored, backed up by swap
+struct Backup {
+    file: NamedTempFile,
+    chunk_sizes: Vec<usize>,
+    file_info: FileBufExt,
+}
+
+// A local file stored by store_path
+struct StoredPath {
+    file_name: String,
+    file_key: String,
+    file_type: String,
+    size: u64,
+    sha256: String,
+}
+
+impl StoredPath {
+    fn names(self) -> (String, String) {
+        (self.file_name, self.file_key)
+    }
+
+    fn into_entry(self) -> ManifestEntry {
+        ManifestEntry {
+            file_key: self.file_key,
+            file_type: self.file_type,
+            size: self.size,
+            sha256: self.sha256,
         }
     }
-    (size, res)
 }
 
-fn build_store_args(
+// The fields shared by every chunk of a file
+#[derive(Clone)]
+struct FileHeader {
     file_key: String,
-    file_extension: String,
-    total_size: u128,
-    data_slice: &Vec<Vec<u8>>,
-    is_open: bool,
-) -> Vec<StoreArgs> {
-    let mut order = 0;
-    let mut puts = vec![];
-    for data in data_slice {
-        puts.push(StoreArgs {
-            key: file_key.clone(),
-            value: data.to_owned(),
-            total_index: Nat::from(data_slice.len() as u128),
-            file_type: file_extension.clone(),
-            total_size: total_size.clone() as u64,
-            is_http_open: is_open,
-            index: Nat::from(order.clone()),
-        });
-        order += 1;
-    }
-    puts
-}
-
-fn get_file_type(file_type: &str) -> &str {
-    if file_type == "pdf" {
-        return "application/pdf";
-    } else if file_type == "jpg" || file_type == "jpeg" {
-        return "image/jpg";
-    } else if file_type == "png" {
-        return "image/png";
-    } else if file_type == "mp4" {
-        return "video/mp4";
-    } else if file_type == "mp3" {
-        return "audio/mp3";
-    } else if file_type == "gif" {
-        return "image/gif";
-    } else if file_type == "txt" {
-        return "text/plain";
-    } else if file_type == "ppt" || file_type == "pptx" {
-        return "application/vnd.ms-powerpoint";
-    } else if file_type == "html" || file_type == "xhtml" {
-        return "text/html";
-    } else if file_type == "doc" || file_type == "docx" {
-        return "application/msword";
-    } else if file_type == "xls" {
-        return "application/x-xls";
-    } else if file_type == "apk" {
-        return "application/vnd.android.package-archive";
-    } else if file_type == "svg" {
-        return "text/xml";
-    } else if file_type == "wmv" {
-        return "video/x-ms-wmv";
-    } else {
-        return "application/octet-stream";
+    file_type: String,
+    total_size: u64,
+    is_http_open: bool,
+    encryption_key: Option<EncryptionKey>,
+}
+
+impl FileHeader {
+    fn total_index(&self) -> usize {
+        total_index(self.total_size)
+    }
+
+    fn chunk_size(&self, index: usize) -> u64 {
+        chunk_size(self.total_size, index)
+    }
+
+    // Size of all the chunks sent, declared to the icsp as the total_size of the file
+    fn stored_size(&self) -> u64 {
+        match self.encryption_key {
+            Some(_) => self.total_size + CHUNK_OVERHEAD * self.total_index() as u64,
+            None => self.total_size,
+        }
+    }
+
+    // Build the chunk at index, encrypted if the header has a key
+    fn chunk(&self, index: usize, value: Vec<u8>) -> StoreArgs {
+        let value = match &self.encryption_key {
+            Some(key) => key.encrypt_chunk(&self.file_key, index, &value),
+            None => value,
+        };
+        StoreArgs {
+            key: self.file_key.clone(),
+            value,
+            total_index: Nat::from(self.total_index()),
+            file_type: self.file_type.clone(),
+            total_size: self.stored_size(),
+            is_http_open: self.is_http_open,
+            index: Nat::from(index),
+        }
+    }
+}
+
+// Read the chunks at the ascending indices from reader as (index, chunk), one chunk at a time,
+// the bytes of the chunks in between are skipped
+fn read_chunks<R: AsyncRead + Unpin>(
+    reader: R,
+    header: FileHeader,
+    indices: Vec<usize>,
+) -> impl Stream<Item = Result<(usize, StoreArgs), IspError>> {
+    stream::try_unfold(
+        (reader, 0, indices.into_iter()),
+        move |(mut reader, position, mut indices)| {
+            let header = header.clone();
+            async move {
+                let Some(index) = indices.next() else {
+                    return Ok(None);
+                };
+                let offset = index as u64 * UPDATE_SIZE as u64;
+                tokio::io::copy(&mut (&mut reader).take(offset - position), &mut sink()).await?;
+                let chunk_size = header.chunk_size(index);
+                let mut value = vec![0; chunk_size as usize];
+                reader.read_exact(&mut value).await?;
+                let chunk = header.chunk(index, value);
+                Ok(Some((
+                    (index, chunk),
+                    (reader, offset + chunk_size, indices),
+                )))
+            }
+        },
+    )
+}
+
+// Count the stored size instead of the original size of a compressed file in the batch
+fn adjust_batch(batch: &mut Option<&mut BatchProgress>, original_size: u64, size: u64) {
+    if let Some(batch) = batch {
+        batch.total_bytes = batch.total_bytes.saturating_sub(original_size) + size;
+    }
+}
+
+// The file information with the sdk metadata removed from file_type
+fn strip_file_info(mut file_info: FileBufExt) -> FileBufExt {
+    file_info.file_type = decode_file_type(&file_info.file_type).0;
+    file_info
+}
+
+// Size of the file before encryption, total_size of an encrypted file counts the stored chunks
+fn content_size(total_size: u64, total_index: &Nat, encrypted: bool) -> u64 {
+    match encrypted {
+        true => total_size.saturating_sub(CHUNK_OVERHEAD * nat_to_usize(total_index) as u64),
+        false => total_size,
+    }
+}
+
+// Size of the chunk at index before encryption
+fn chunk_size(total_size: u64, index: usize) -> u64 {
+    let offset = index as u64 * UPDATE_SIZE as u64;
+    total_size.saturating_sub(offset).min(UPDATE_SIZE as u64)
+}
+
+// Number of chunks of a file, an empty file is stored as one empty chunk like an empty str
+fn total_index(total_size: u64) -> usize {
+    (total_size.div_ceil(UPDATE_SIZE as u64) as usize).max(1)
+}
+
+// The indices of the chunks holding the len > 0 bytes at offset of a file of total_index
+// chunks, with the number of bytes of the first chunk before offset
+fn range_chunks(offset: u64, len: usize, total_index: usize) -> (Range<u64>, usize) {
+    let first_index = offset / UPDATE_SIZE as u64;
+    let last_index = offset.saturating_add(len as u64 - 1) / UPDATE_SIZE as u64;
+    let skip = (offset - first_index * UPDATE_SIZE as u64) as usize;
+    (first_index..(last_index + 1).min(total_index as u64), skip)
+}
+
+// The len bytes after skip of the chunks got by range_chunks, fewer at the end of the file
+fn slice_range(mut data: Vec<u8>, skip: usize, len: usize) -> Vec<u8> {
+    let start = skip.min(data.len());
+    let end = (start + len).min(data.len());
+    data.truncate(end);
+    data.split_off(start)
+}
+
+fn nat_to_usize(nat: &Nat) -> usize {
+    usize::try_from(&nat.0).unwrap_or(usize::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_send<T: Send>(_: T) {}
+
+    // The futures of the handle can be spawned, checked at compile time
+    #[allow(dead_code, unused_must_use)]
+    fn futures_are_send(icsp: IcspHandle, scan: GarbageScan) {
+        assert_send(icsp.store_file("a.txt", false));
+        assert_send(icsp.store_files("dir", false));
+        assert_send(icsp.store_dir("dir", false));
+        assert_send(icsp.deploy_dir("dist"));
+        assert_send(icsp.sync_dir("dir", "manifest.json", SyncOptions::default()));
+        assert_send(icsp.store_str("data", false));
+        assert_send(icsp.store_bytes(b"data", "text/plain", None, false));
+        assert_send(icsp.store_reader(&b"data"[..], 4, "text/plain", false, "key".to_string()));
+        assert_send(icsp.resume_upload("key", &b"data"[..]));
+        assert_send(icsp.replace_str("key", "data", false));
+        assert_send(icsp.replace_bytes("key", b"data", "text/plain", false));
+        assert_send(icsp.replace_file("key", "a.txt", false));
+        assert_send(icsp.get_file("key"));
+        assert_send(icsp.get_file_to_path("key", "a.txt"));
+        assert_send(icsp.read_range("key", 0, 1));
+        assert_send(icsp.open_file("key"));
+        assert_send(icsp.scan_garbage());
+        assert_send(icsp.collect_garbage(&scan, Duration::ZERO));
+        assert_send(replace_str(
+            "identity.pem",
+            "key",
+            "data",
+            "canister",
+            false,
+        ));
+    }
+
+    fn header(total_size: u64, encryption_key: Option<EncryptionKey>) -> FileHeader {
+        FileHeader {
+            file_key: "key".to_string(),
+            file_type: "application/octet-stream".to_string(),
+            total_size,
+            is_http_open: false,
+            encryption_key,
+        }
+    }
+
+    // Every chunk of the header as sent to the icsp
+    fn chunks(header: &FileHeader) -> Vec<StoreArgs> {
+        (0..header.total_index())
+            .map(|index| header.chunk(index, vec![7; header.chunk_size(index) as usize]))
+            .collect()
+    }
+
+    #[test]
+    fn declared_size_is_the_size_of_the_chunks_sent() {
+        let key = EncryptionKey::from_bytes([1; 32]);
+        for total_size in [0, 1, UPDATE_SIZE as u64, 2 * UPDATE_SIZE as u64 + 5] {
+            for encryption_key in [None, Some(key.clone())] {
+                let header = header(total_size, encryption_key);
+                let chunks = chunks(&header);
+                let sent: u64 = chunks.iter().map(|put| put.value.len() as u64).sum();
+                for put in &chunks {
+                    assert_eq!(put.total_size, sent);
+                    assert_eq!(put.total_index, Nat::from(chunks.len()));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn content_size_undoes_the_encryption_overhead() {
+        let key = EncryptionKey::from_bytes([1; 32]);
+        for total_size in [0, 1, UPDATE_SIZE as u64, 2 * UPDATE_SIZE as u64 + 5] {
+            let plain = header(total_size, None);
+            let total_index = Nat::from(plain.total_index());
+            assert_eq!(
+                content_size(plain.stored_size(), &total_index, false),
+                total_size
+            );
+            let encrypted = header(total_size, Some(key.clone()));
+            assert_eq!(
+                content_size(encrypted.stored_size(), &total_index, true),
+                total_size
+            );
+        }
+    }
+
+    #[test]
+    fn chunks_split_the_file_at_update_size() {
+        let size = UPDATE_SIZE as u64;
+        assert_eq!(total_index(0), 1);
+        assert_eq!(total_index(1), 1);
+        assert_eq!(total_index(size), 1);
+        assert_eq!(total_index(size + 1), 2);
+        assert_eq!(chunk_size(0, 0), 0);
+        assert_eq!(chunk_size(size + 1, 0), size);
+        assert_eq!(chunk_size(size + 1, 1), 1);
+        assert_eq!(chunk_size(size + 1, 2), 0);
+        for total_size in [0, 1, size - 1, size, 3 * size + 5] {
+            let sizes = (0..total_index(total_size)).map(|index| chunk_size(total_size, index));
+            assert_eq!(sizes.sum::<u64>(), total_size);
+        }
+    }
+
+    #[test]
+    fn range_chunks_cover_the_range() {
+        let size = UPDATE_SIZE as u64;
+        assert_eq!(range_chunks(0, 1, 3), (0..1, 0));
+        assert_eq!(range_chunks(size - 1, 2, 3), (0..2, UPDATE_SIZE - 1));
+        assert_eq!(range_chunks(size, UPDATE_SIZE, 3), (1..2, 0));
+        assert_eq!(range_chunks(size + 5, UPDATE_SIZE, 3), (1..3, 5));
+        // past the end of the file
+        assert_eq!(range_chunks(2 * size + 5, 2 * UPDATE_SIZE, 3), (2..3, 5));
+        assert!(range_chunks(3 * size, 1, 3).0.is_empty());
+        assert!(range_chunks(u64::MAX, usize::MAX, 3).0.is_empty());
+    }
+
+    #[test]
+    fn read_range_matches_the_file() {
+        let total_size = 2 * UPDATE_SIZE + 100;
+        let file: Vec<u8> = (0..total_size).map(|i| (i % 251) as u8).collect();
+        let chunks: Vec<&[u8]> = file.chunks(UPDATE_SIZE).collect();
+        let read = |offset: u64, len: usize| {
+            let (indices, skip) = range_chunks(offset, len, chunks.len());
+            let data = indices.flat_map(|index| chunks[index as usize].to_vec());
+            slice_range(data.collect(), skip, len)
+        };
+        let size = UPDATE_SIZE as u64;
+        for (offset, len) in [
+            (0, 10),
+            (size - 3, 6),
+            (size, UPDATE_SIZE),
+            (5, 2 * UPDATE_SIZE),
+            (2 * size + 90, 50),
+            (3 * size, 5),
+        ] {
+            let start = (offset as usize).min(total_size);
+            let end = (start + len).min(total_size);
+            assert_eq!(
+                read(offset, len),
+                file[start..end],
+                "{} bytes at {}",
+                len,
+                offset
+            );
+        }
     }
 }
@@ -0,0 +1,192 @@
+use std::sync::Arc;
+
+/// Progress of one file transfer, reported after every chunk stored or got
+///
+/// Set the callback with [`IcspHandle::with_progress`](crate::IcspHandle::with_progress).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Progress {
+    /// Key of the file being transferred
+    pub file_key: String,
+    /// Bytes of the file transferred so far
+    pub bytes_done: u64,
+    /// Size of the file, 0 when the icsp does not know the size of a downloaded file
+    pub total_bytes: u64,
+    /// Chunks of the file transferred so far
+    pub chunks_done: usize,
+    /// Number of chunks of the file
+    pub total_chunks: usize,
+    /// Progress of the whole batch when the file is stored by `store_files` or `store_dir`
+    pub batch: Option<BatchProgress>,
+}
+
+/// Progress of all the files stored by one `store_files` or `store_dir` call
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BatchProgress {
+    /// Files completely stored so far
+    pub files_done: usize,
+    /// Number of files of the batch
+    pub total_files: usize,
+    /// Bytes of all the files stored so far
+    pub bytes_done: u64,
+    /// Size of all the files of the batch
+    pub total_bytes: u64,
+}
+
+pub(crate) type ProgressCallback = Arc<dyn Fn(&Progress) + Send + Sync>;
+
+// Counts the chunks of one file and reports every chunk to the callback
+pub(crate) struct ProgressTracker<'a> {
+    callback: Option<&'a ProgressCallback>,
+    progress: Progress,
+    batch: Option<&'a mut BatchProgress>,
+}
+
+impl<'a> ProgressTracker<'a> {
+    pub(crate) fn new(
+        callback: Option<&'a ProgressCallback>,
+        file_key: &str,
+        total_bytes: u64,
+        total_chunks: usize,
+        batch: Option<&'a mut BatchProgress>,
+    ) -> ProgressTracker<'a> {
+        ProgressTracker {
+            callback,
+            progress: Progress {
+                file_key: file_key.to_string(),
+                bytes_done: 0,
+                total_bytes,
+                chunks_done: 0,
+                total_chunks,
+                batch: None,
+            },
+            batch,
+        }
+    }
+
+    pub(crate) fn total_bytes(&self) -> u64 {
+        self.progress.total_bytes
+    }
+
+    // Count chunks transferred before, e.g. by an interrupted upload, without reporting them
+    pub(crate) fn skip(&mut self, bytes: u64, chunks: usize) {
+        self.count(bytes, chunks);
+    }
+
+    pub(crate) fn chunk_done(&mut self, bytes: u64) {
+        self.count(bytes, 1);
+        self.report();
+    }
+
+    // Count and report the rest of the file transferred at once, e.g. by a replace
+    pub(crate) fn file_done(&mut self) {
+        let bytes = self
+            .progress
+            .total_bytes
+            .saturating_sub(self.progress.bytes_done);
+        let chunks = self
+            .progress
+            .total_chunks
+            .saturating_sub(self.progress.chunks_done);
+        self.count(bytes, chunks);
+        self.report();
+    }
+
+    fn report(&mut self) {
+        if let Some(callback) = self.callback {
+            self.progress.batch = self.batch.as_deref().copied();
+            callback(&self.progress);
+        }
+    }
+
+    fn count(&mut self, bytes: u64, chunks: usize) {
+        self.progress.bytes_done += bytes;
+        self.progress.chunks_done += chunks;
+        if let Some(batch) = self.batch.as_deref_mut() {
+            batch.bytes_done += bytes;
+            if chunks > 0 && self.progress.chunks_done == self.progress.total_chunks {
+                batch.files_done += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    // A callback and the progress it received
+    fn recorder() -> (ProgressCallback, Arc<Mutex<Vec<Progress>>>) {
+        let reported = Arc::new(Mutex::new(Vec::new()));
+        let sink = reported.clone();
+        let callback: ProgressCallback =
+            Arc::new(move |progress: &Progress| sink.lock().unwrap().push(progress.clone()));
+        (callback, reported)
+    }
+
+    #[test]
+    fn reports_every_chunk_and_counts_the_file_once() {
+        let (callback, reported) = recorder();
+        let mut batch = BatchProgress {
+            total_files: 2,
+            total_bytes: 15,
+            ..BatchProgress::default()
+        };
+        let mut tracker = ProgressTracker::new(Some(&callback), "key", 10, 2, Some(&mut batch));
+        tracker.chunk_done(6);
+        tracker.chunk_done(4);
+        drop(tracker);
+        let reported = reported.lock().unwrap();
+        assert_eq!(reported.len(), 2);
+        assert_eq!((reported[0].bytes_done, reported[0].chunks_done), (6, 1));
+        assert_eq!(reported[0].batch.unwrap().files_done, 0);
+        assert_eq!((reported[1].bytes_done, reported[1].chunks_done), (10, 2));
+        assert_eq!(reported[1].batch, Some(batch));
+        assert_eq!((batch.files_done, batch.bytes_done), (1, 10));
+    }
+
+    #[test]
+    fn skipped_chunks_are_counted_but_not_reported() {
+        let (callback, reported) = recorder();
+        let mut batch = BatchProgress::default();
+        let mut tracker = ProgressTracker::new(Some(&callback), "key", 10, 3, Some(&mut batch));
+        tracker.skip(8, 2);
+        assert!(reported.lock().unwrap().is_empty());
+        tracker.chunk_done(2);
+        drop(tracker);
+        let last = reported.lock().unwrap().last().cloned().unwrap();
+        assert_eq!((last.bytes_done, last.chunks_done), (10, 3));
+        assert_eq!((batch.files_done, batch.bytes_done), (1, 10));
+    }
+
+    #[test]
+    fn skipping_every_chunk_completes_the_file() {
+        let mut batch = BatchProgress::default();
+        let mut tracker = ProgressTracker::new(None, "key", 10, 2, Some(&mut batch));
+        tracker.skip(10, 2);
+        tracker.skip(0, 0);
+        drop(tracker);
+        assert_eq!((batch.files_done, batch.bytes_done), (1, 10));
+    }
+
+    #[test]
+    fn file_done_reports_the_rest_of_the_file() {
+        let (callback, reported) = recorder();
+        let mut batch = BatchProgress::default();
+        let mut tracker = ProgressTracker::new(Some(&callback), "key", 10, 2, Some(&mut batch));
+        tracker.chunk_done(4);
+        tracker.file_done();
+        drop(tracker);
+        let last = reported.lock().unwrap().last().cloned().unwrap();
+        assert_eq!((last.bytes_done, last.chunks_done), (10, 2));
+        assert_eq!((batch.files_done, batch.bytes_done), (1, 10));
+    }
+
+    #[test]
+    fn works_without_a_batch() {
+        let (callback, reported) = recorder();
+        let mut tracker = ProgressTracker::new(Some(&callback), "key", 3, 1, None);
+        tracker.chunk_done(3);
+        assert_eq!(reported.lock().unwrap()[0].batch, None);
+    }
+}
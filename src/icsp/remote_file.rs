@@ -0,0 +1,272 @@
+use super::{IcspHandle, UPDATE_SIZE};
+use crate::IspError;
+use futures::future::BoxFuture;
+use futures::{ready, FutureExt};
+use std::collections::VecDeque;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt, ReadBuf};
+use tokio::runtime::Handle;
+
+const CACHE_CHUNKS: usize = 8;
+
+type ChunkFetch = BoxFuture<'static, Result<Vec<u8>, IspError>>;
+
+/// A file stored in an icsp, read and seeked like a local file, created by [`IcspHandle::open_file`]
+///
+/// Chunks are fetched from the bucket when they are first read, the last 8 chunks read are
+/// cached. Use [`RemoteFile::into_blocking`] for libraries that need [`Read`] + [`Seek`].
+///
+/// # Examples
+///
+/// ``` no_run
+/// use isp_sdk::{IcspHandle, IspError};
+/// use std::io::SeekFrom;
+/// use tokio::io::{AsyncReadExt, AsyncSeekExt};
+///
+/// pub async fn read_trailer(icsp: &IcspHandle, file_key: &str) -> Result<(), IspError> {
+///     let mut file = icsp.open_file(file_key).await?;
+///     file.seek(SeekFrom::End(-1024)).await?;
+///     let mut trailer = Vec::new();
+///     file.read_to_end(&mut trailer).await?;
+///     println!("trailer:{:?}", trailer);
+///     Ok(())
+/// }
+/// ```
+pub struct RemoteFile {
+    handle: IcspHandle,
+    file_key: String,
+    bucket_canister_id: candid::Principal,
+    total_size: u64,
+    position: u64,
+    cache: VecDeque<(usize, Vec<u8>)>,
+    pending: Option<(usize, ChunkFetch)>,
+}
+
+impl RemoteFile {
+    pub(crate) fn new(
+        handle: IcspHandle,
+        file_key: String,
+        bucket_canister_id: candid::Principal,
+        total_size: u64,
+    ) -> RemoteFile {
+        RemoteFile {
+            handle,
+            file_key,
+            bucket_canister_id,
+            total_size,
+            position: 0,
+            cache: VecDeque::new(),
+            pending: None,
+        }
+    }
+
+    /// Key of the file
+    pub fn file_key(&self) -> &str {
+        &self.file_key
+    }
+
+    /// Size of the file in bytes
+    pub fn len(&self) -> u64 {
+        self.total_size
+    }
+
+    /// Whether the file is empty
+    pub fn is_empty(&self) -> bool {
+        self.total_size == 0
+    }
+
+    /// Turn into a [`Read`] + [`Seek`] file which blocks on runtime to fetch chunks
+    ///
+    /// The blocking file must not be used on a thread running async tasks of the runtime,
+    /// use it in [`tokio::task::spawn_blocking`] or on a thread of its own.
+    pub fn into_blocking(self, runtime: Handle) -> BlockingRemoteFile {
+        BlockingRemoteFile {
+            file: self,
+            runtime,
+        }
+    }
+
+    fn cached(&self, index: usize) -> Option<&[u8]> {
+        self.cache
+            .iter()
+            .find(|(cached_index, _)| *cached_index == index)
+            .map(|(_, chunk)| chunk.as_slice())
+    }
+
+    // Fetch the chunk at index into the cache
+    fn poll_chunk(&mut self, cx: &mut Context<'_>, index: usize) -> Poll<io::Result<()>> {
+        if !matches!(self.pending, Some((pending_index, _)) if pending_index == index) {
+            let handle = self.handle.clone();
+            let bucket_canister_id = self.bucket_canister_id;
+            let file_key = self.file_key.clone();
+            let fetch = async move {
+                let (chunk, _) = handle
+                    .get_chunk(bucket_canister_id, &file_key, index)
+                    .await?;
+                Ok(chunk)
+            };
+            self.pending = Some((index, fetch.boxed()));
+        }
+        let (_, fetch) = self.pending.as_mut().expect("pending chunk");
+        let chunk = ready!(fetch.poll_unpin(cx));
+        self.pending = None;
+        let chunk = chunk.map_err(io::Error::other)?;
+        if chunk.is_empty() {
+            return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+        }
+        if self.cache.len() == CACHE_CHUNKS {
+            self.cache.pop_front();
+        }
+        self.cache.push_back((index, chunk));
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncRead for RemoteFile {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if this.position >= this.total_size || buf.remaining() == 0 {
+            return Poll::Ready(Ok(()));
+        }
+        let index = (this.position / UPDATE_SIZE as u64) as usize;
+        if this.cached(index).is_none() {
+            if let Err(error) = ready!(this.poll_chunk(cx, index)) {
+                return Poll::Ready(Err(error));
+            }
+        }
+        let chunk = this.cached(index).expect("cached chunk");
+        let data = chunk_slice(chunk, this.position, this.total_size, buf.remaining())?;
+        buf.put_slice(data);
+        this.position += data.len() as u64;
+        Poll::Ready(Ok(()))
+    }
+}
+
+// The next at most max bytes at position < total_size of the file from the chunk holding
+// position, an error if the chunk ends before them as when stored with another chunk size
+fn chunk_slice(chunk: &[u8], position: u64, total_size: u64, max: usize) -> io::Result<&[u8]> {
+    let offset = (position % UPDATE_SIZE as u64) as usize;
+    if offset >= chunk.len() {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "chunk ends before the end of the file",
+        ));
+    }
+    let left = usize::try_from(total_size - position).unwrap_or(usize::MAX);
+    let len = (chunk.len() - offset).min(max).min(left);
+    Ok(&chunk[offset..offset + len])
+}
+
+impl AsyncSeek for RemoteFile {
+    fn start_seek(self: Pin<&mut Self>, position: SeekFrom) -> io::Result<()> {
+        let this = self.get_mut();
+        let position = match position {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => this.total_size.checked_add_signed(offset),
+            SeekFrom::Current(offset) => this.position.checked_add_signed(offset),
+        };
+        this.position = position.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )
+        })?;
+        Ok(())
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        Poll::Ready(Ok(self.position))
+    }
+}
+
+/// A [`RemoteFile`] implementing [`Read`] + [`Seek`], created by [`RemoteFile::into_blocking`]
+///
+/// # Examples
+///
+/// ``` no_run
+/// use isp_sdk::{IcspHandle, IspError};
+/// use std::io::Read;
+///
+/// pub async fn read_file(icsp: &IcspHandle, file_key: &str) -> Result<Vec<u8>, IspError> {
+///     let file = icsp.open_file(file_key).await?;
+///     let mut file = file.into_blocking(tokio::runtime::Handle::current());
+///     let data = tokio::task::spawn_blocking(move || {
+///         let mut data = Vec::new();
+///         file.read_to_end(&mut data).map(|_| data)
+///     })
+///     .await
+///     .expect("read task panicked")?;
+///     Ok(data)
+/// }
+/// ```
+pub struct BlockingRemoteFile {
+    file: RemoteFile,
+    runtime: Handle,
+}
+
+impl BlockingRemoteFile {
+    /// The async file read by this file
+    pub fn into_inner(self) -> RemoteFile {
+        self.file
+    }
+}
+
+impl Read for BlockingRemoteFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.runtime.block_on(self.file.read(buf))
+    }
+}
+
+impl Seek for BlockingRemoteFile {
+    fn seek(&mut self, position: SeekFrom) -> io::Result<u64> {
+        self.runtime.block_on(self.file.seek(position))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: u64 = UPDATE_SIZE as u64;
+
+    #[test]
+    fn slices_the_chunk_at_position() {
+        let chunk: Vec<u8> = (0..UPDATE_SIZE).map(|i| (i % 251) as u8).collect();
+        let total_size = 2 * SIZE + 10;
+        assert_eq!(chunk_slice(&chunk, 0, total_size, 4).unwrap(), &chunk[..4]);
+        assert_eq!(chunk_slice(&chunk, 5, total_size, 4).unwrap(), &chunk[5..9]);
+        // a chunk is never read past its end or the next chunk
+        let tail = chunk_slice(&chunk, SIZE - 3, total_size, 10).unwrap();
+        assert_eq!(tail, &chunk[UPDATE_SIZE - 3..]);
+        assert_eq!(
+            chunk_slice(&chunk, SIZE + 7, total_size, 2).unwrap(),
+            &chunk[7..9]
+        );
+    }
+
+    #[test]
+    fn stops_at_the_end_of_the_file() {
+        let last = vec![1; 10];
+        let total_size = 2 * SIZE + 10;
+        let data = chunk_slice(&last, 2 * SIZE + 4, total_size, 100).unwrap();
+        assert_eq!(data, &last[4..]);
+        // a last chunk longer than the file is not read past the end
+        let data = chunk_slice(&last, 2 * SIZE + 4, 2 * SIZE + 6, 100).unwrap();
+        assert_eq!(data.len(), 2);
+    }
+
+    #[test]
+    fn short_chunk_before_the_end_is_an_error() {
+        let short = vec![1; 1024];
+        let error = chunk_slice(&short, 1024, 2 * SIZE, 10).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+        assert!(chunk_slice(&short, SIZE + 2000, 2 * SIZE, 10).is_err());
+        assert!(chunk_slice(&[], 0, 1, 10).is_err());
+    }
+}
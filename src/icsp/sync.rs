@@ -0,0 +1,74 @@
+use super::Manifest;
+use std::fmt;
+
+/// What [`IcspHandle::sync_dir`](crate::IcspHandle::sync_dir) may change in the icsp
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SyncOptions {
+    /// Store the uploaded files http open
+    pub is_http_open: bool,
+    /// Delete the remote files of the manifest whose local file was removed
+    pub delete_removed: bool,
+    /// Only compare, upload, delete and write the manifest nothing
+    pub dry_run: bool,
+}
+
+/// Differences between a local directory and the icsp found by [`IcspHandle::sync_dir`](crate::IcspHandle::sync_dir)
+///
+/// Every list holds relative paths in path order. Display shows one line per change,
+/// `+` for added, `~` for changed and `-` for removed files.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SyncReport {
+    /// Local files the icsp does not have
+    pub added: Vec<String>,
+    /// Local files whose content or type differs from the remote file
+    pub changed: Vec<String>,
+    /// Local files the icsp already has
+    pub unchanged: Vec<String>,
+    /// Remote files of the manifest whose local file was removed
+    pub removed: Vec<String>,
+    /// Staging copies of changed files that could not be deleted
+    pub staging_keys: Vec<String>,
+    /// Whether removed files are deleted from the icsp
+    pub delete_removed: bool,
+    /// Whether the report only shows what would change
+    pub dry_run: bool,
+    /// The manifest after the sync, or that would be written when dry_run
+    pub manifest: Manifest,
+}
+
+impl SyncReport {
+    /// Whether the local directory and the icsp already match
+    pub fn is_in_sync(&self) -> bool {
+        self.added.is_empty()
+            && self.changed.is_empty()
+            && (self.removed.is_empty() || !self.delete_removed)
+    }
+}
+
+impl fmt::Display for SyncReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for path in &self.added {
+            writeln!(f, "+ {}", path)?;
+        }
+        for path in &self.changed {
+            writeln!(f, "~ {}", path)?;
+        }
+        for path in &self.removed {
+            match self.delete_removed {
+                true => writeln!(f, "- {}", path)?,
+                false => writeln!(f, "- {} (kept in icsp)", path)?,
+            }
+        }
+        if self.dry_run {
+            write!(f, "dry run: ")?;
+        }
+        write!(
+            f,
+            "{} added, {} changed, {} unchanged, {} removed",
+            self.added.len(),
+            self.changed.len(),
+            self.unchanged.len(),
+            self.removed.len()
+        )
+    }
+}
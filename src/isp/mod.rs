@@ -1,41 +1,32 @@
 mod isp_did;
-use candid::{CandidType, Decode, Encode, Nat};
-use garcon::Delay;
-use hex::{self};
-use ic_agent::agent::http_transport::ReqwestHttpReplicaV2Transport;
-use ic_agent::{identity::Secp256k1Identity, Agent};
-pub use isp_did::{CreateICSPResult, Error, TopUpArgs, TopUpResult, TransferResult};
+use crate::icsp::InitResult;
+use crate::{IspClient, IspError};
+use candid::{CandidType, Decode, Encode};
+pub use isp_did::{CreateICSPResult, Error, TopUpArgs, TopUpResult, TransferError, TransferResult};
 use serde::Deserialize;
 
-static ISP_CANISTER_ID_TEXT: &'static str = "p2pki-xyaaa-aaaan-qatua-cai";
-
 /// Get icsps of user, return Vec<(icsp_name, icsp_canister_id)>
 ///
 /// # Examples
 ///
 /// ``` no_run
-/// use isp_sdk::isp;
+/// use isp_sdk::{isp, IspError};
 ///
-/// pub async fn get_user_icsps() {
-///     let response = isp::get_user_icsps("identities/identity.pem").await;
+/// pub async fn get_user_icsps() -> Result<(), IspError> {
+///     let response = isp::get_user_icsps("identities/identity.pem").await?;
 ///     for i in &response {
 ///         println!("icsp_name:{:?},icsp_canister_id:{:?}", i.0, i.1.to_text());
 ///     }
 ///     if response.is_empty() {
 ///         println!("user do not have icsp\n");
 ///     }
+///     Ok(())
 /// }
 /// ```
-pub async fn get_user_icsps(pem_identity_path: &str) -> Vec<(String, candid::Principal)> {
-    let canister_id = candid::Principal::from_text(ISP_CANISTER_ID_TEXT).unwrap();
-    let response_blob = build_agent(pem_identity_path)
-        .query(&canister_id, "getUserICSPs")
-        .with_arg(Encode!().expect("encode error"))
-        .call()
-        .await
-        .expect("response error");
-    let response = Decode!(&response_blob, Vec<(String, candid::Principal)>).unwrap();
-    response
+pub async fn get_user_icsps(
+    pem_identity_path: &str,
+) -> Result<Vec<(String, candid::Principal)>, IspError> {
+    IspClient::new(pem_identity_path)?.get_user_icsps().await
 }
 
 /// Get user's subAccount of the isp
@@ -45,25 +36,18 @@ pub async fn get_user_icsps(pem_identity_path: &str) -> Vec<(String, candid::Pri
 /// # Examples
 ///
 /// ``` no_run
-/// use isp_sdk::isp;
+/// use isp_sdk::{isp, IspError};
 ///
-/// pub async fn get_sub_account() {
+/// pub async fn get_sub_account() -> Result<(), IspError> {
 ///     println!(
 ///         "SubAccount:{:?}\n",
-///         isp::get_sub_account("identities/identity.pem").await
+///         isp::get_sub_account("identities/identity.pem").await?
 ///     );
+///     Ok(())
 /// }
 /// ```
-pub async fn get_sub_account(pem_identity_path: &str) -> String {
-    let canister_id = candid::Principal::from_text(ISP_CANISTER_ID_TEXT).unwrap();
-    let response_blob = build_agent(pem_identity_path)
-        .query(&canister_id, "getSubAccount")
-        .with_arg(Encode!().expect("encode error"))
-        .call()
-        .await
-        .expect("response error");
-    let response = Decode!(&response_blob, Vec<u8>).unwrap();
-    hex::encode(response)
+pub async fn get_sub_account(pem_identity_path: &str) -> Result<String, IspError> {
+    IspClient::new(pem_identity_path)?.get_sub_account().await
 }
 
 /// Get the icp balance of user's subAccount of the isp
@@ -73,25 +57,20 @@ pub async fn get_sub_account(pem_identity_path: &str) -> String {
 /// # Examples
 ///
 /// ``` no_run
-/// use isp_sdk::isp;
+/// use isp_sdk::{isp, IspError};
 ///
-/// pub async fn get_user_sub_account_icp_balance() {
+/// pub async fn get_user_sub_account_icp_balance() -> Result<(), IspError> {
 ///     println!(
 ///         "icp balance:{:?}\n",
-///         isp::get_user_sub_account_icp_balance("identities/identity.pem").await
+///         isp::get_user_sub_account_icp_balance("identities/identity.pem").await?
 ///     );
+///     Ok(())
 /// }
 /// ```
-pub async fn get_user_sub_account_icp_balance(pem_identity_path: &str) -> u64 {
-    let canister_id = candid::Principal::from_text(ISP_CANISTER_ID_TEXT).unwrap();
-    let response_blob = build_agent(pem_identity_path)
-        .update(&canister_id, "getUserSubAccountICPBalance")
-        .with_arg(Encode!().expect("encode error"))
-        .call_and_wait()
+pub async fn get_user_sub_account_icp_balance(pem_identity_path: &str) -> Result<u64, IspError> {
+    IspClient::new(pem_identity_path)?
+        .get_user_sub_account_icp_balance()
         .await
-        .expect("response error");
-    let response = Decode!(&response_blob, u64).unwrap();
-    response
 }
 
 /// Transfer out icp from user's subAccount of the isp
@@ -101,9 +80,9 @@ pub async fn get_user_sub_account_icp_balance(pem_identity_path: &str) -> u64 {
 /// # Examples
 ///
 /// ``` no_run
-/// use isp_sdk::isp;
+/// use isp_sdk::{isp, IspError};
 ///
-/// pub async fn transfer_out_user_sub_account_icp() {
+/// pub async fn transfer_out_user_sub_account_icp() -> Result<(), IspError> {
 ///     println!(
 ///         "transfer out icp result:{:?}\n",
 ///         isp::transfer_out_user_sub_account_icp(
@@ -111,24 +90,19 @@ pub async fn get_user_sub_account_icp_balance(pem_identity_path: &str) -> u64 {
 ///             "3eee9b4671b8fde5a501288d74d21ee93042dc202104fa35051563ae35d24f2f",
 ///             5000000 as u64
 ///         )
-///         .await
+///         .await?
 ///     );
+///     Ok(())
 /// }
 /// ```
 pub async fn transfer_out_user_sub_account_icp(
     pem_identity_path: &str,
     to: &str,
     amount: u64,
-) -> TransferResult {
-    let canister_id = candid::Principal::from_text(ISP_CANISTER_ID_TEXT).unwrap();
-    let response_blob = build_agent(pem_identity_path)
-        .update(&canister_id, "transferOutUserSubAccountICP")
-        .with_arg(Encode!(&(hex::decode(to).unwrap()), &amount).expect("encode error"))
-        .call_and_wait()
+) -> Result<u64, IspError> {
+    IspClient::new(pem_identity_path)?
+        .transfer_out_user_sub_account_icp(to, amount)
         .await
-        .expect("response error");
-    let response = Decode!(&response_blob, TransferResult).unwrap();
-    response
 }
 
 /// Get admins of isp
@@ -137,25 +111,18 @@ pub async fn transfer_out_user_sub_account_icp(
 ///
 /// ``` no_run
 /// use candid::Principal;
-/// use isp_sdk::isp;
+/// use isp_sdk::{isp, IspError};
 ///
-/// pub async fn get_isp_admins() {
+/// pub async fn get_isp_admins() -> Result<(), IspError> {
 ///     println!("isp admins:");
-///     for i in &isp::get_isp_admins("identities/identity.pem").await {
+///     for i in &isp::get_isp_admins("identities/identity.pem").await? {
 ///         println!("{:?}", Principal::to_text(i));
 ///     }
+///     Ok(())
 /// }
 /// ```
-pub async fn get_isp_admins(pem_identity_path: &str) -> Vec<candid::Principal> {
-    let canister_id = candid::Principal::from_text(ISP_CANISTER_ID_TEXT).unwrap();
-    let response_blob = build_agent(pem_identity_path)
-        .query(&canister_id, "getAdmins")
-        .with_arg(Encode!().expect("encode error"))
-        .call()
-        .await
-        .expect("response error");
-    let response = Decode!(&response_blob, Vec<candid::Principal>).unwrap();
-    response
+pub async fn get_isp_admins(pem_identity_path: &str) -> Result<Vec<candid::Principal>, IspError> {
+    IspClient::new(pem_identity_path)?.get_isp_admins().await
 }
 
 /// Get ISP's ICSP_WASM version
@@ -163,24 +130,18 @@ pub async fn get_isp_admins(pem_identity_path: &str) -> Vec<candid::Principal> {
 /// # Examples
 ///
 /// ``` no_run
-/// use isp_sdk::isp;
+/// use isp_sdk::{isp, IspError};
 ///
-/// pub async fn get_isp_version() {
+/// pub async fn get_isp_version() -> Result<(), IspError> {
 ///     println!(
 ///         "isp version: {:?}",
-///         isp::get_version("identities/identity.pem").await
+///         isp::get_version("identities/identity.pem").await?
 ///     );
+///     Ok(())
 /// }
 /// ```
-pub async fn get_version(pem_identity_path: &str) -> String {
-    let canister_id = candid::Principal::from_text(ISP_CANISTER_ID_TEXT).unwrap();
-    let response_blob = build_agent(pem_identity_path)
-        .query(&canister_id, "getVersion")
-        .with_arg(Encode!().expect("encode error"))
-        .call()
-        .await
-        .expect("response error");
-    Decode!(&response_blob, String).unwrap()
+pub async fn get_version(pem_identity_path: &str) -> Result<String, IspError> {
+    IspClient::new(pem_identity_path)?.get_isp_version().await
 }
 
 /// Use icp to create a icsp canister and use the [XTC](https://github.com/Psychedelic/dank/tree/main/xtc) to top_up it
@@ -200,7 +161,7 @@ pub async fn get_version(pem_identity_path: &str) -> String {
 /// # Examples
 ///
 /// ``` no_run
-/// use isp_sdk::isp::{self, CreateICSPResult};
+/// use isp_sdk::{isp, IspError};
 ///
 /// pub async fn create_icsp() {
 ///     let response = isp::create_icsp(
@@ -210,13 +171,16 @@ pub async fn get_version(pem_identity_path: &str) -> String {
 ///         5_000_000_000_000 as u64 - 2_000_000_000 as u64,
 ///     )
 ///         .await;
-///     match response.0 {
-///         CreateICSPResult::ok(canister_id) => {
+///     match response {
+///         Ok(canister_id) => {
+///             println!("create icsp success: {:?}", canister_id.to_text());
+///         }
+///         Err(IspError::Created { canister_id, error }) => {
 ///             println!("create icsp success: {:?}", canister_id.to_text());
-///             println!("use XTC topup result: {:?}", response.1.unwrap());
+///             println!("top up or init error: {}", error);
 ///         }
-///         CreateICSPResult::err(error) => {
-///             println!("create icsp error: {:?}", error);
+///         Err(error) => {
+///             println!("create icsp error: {}", error);
 ///         }
 ///     }
 /// }
@@ -226,57 +190,10 @@ pub async fn create_icsp(
     icsp_name: &str,
     icp_to_create_amount: u64,
     xtc_to_topup_amount: u64,
-) -> (CreateICSPResult, Option<BurnResult>) {
-    // create a icsp canister
-    let isp_canister_id = candid::Principal::from_text(ISP_CANISTER_ID_TEXT).unwrap();
-    let agent = build_agent(pem_identity_path);
-    let response_blob = agent
-        .update(&isp_canister_id, "createICSP")
-        .with_arg(Encode!(&icsp_name, &icp_to_create_amount).expect("encode error"))
-        .call_and_wait()
+) -> Result<candid::Principal, IspError> {
+    IspClient::new(pem_identity_path)?
+        .create_icsp(icsp_name, icp_to_create_amount, xtc_to_topup_amount)
         .await
-        .expect("response error");
-    let response = Decode!(&response_blob, CreateICSPResult).unwrap();
-    match response {
-        CreateICSPResult::ok(icsp_canister_id) => {
-            // use XTC to topup icsp
-            let top_up_response = top_up_icsp_with_xtc(
-                pem_identity_path,
-                BurnArgs {
-                    canister_id: icsp_canister_id,
-                    amount: xtc_to_topup_amount,
-                },
-            )
-            .await;
-            match top_up_response {
-                BurnResult::Ok(block_index) => {
-                    // init icsp
-                    let _init_response = agent
-                        .update(
-                            &candid::Principal::from_text(icsp_canister_id.to_text()).unwrap(),
-                            "init",
-                        )
-                        .with_arg(Encode!().expect("encode error"))
-                        .call_and_wait()
-                        .await
-                        .expect("response error");
-                    return (
-                        CreateICSPResult::ok(icsp_canister_id),
-                        Some(BurnResult::Ok(block_index)),
-                    );
-                }
-                BurnResult::Err(burn_err) => {
-                    return (
-                        CreateICSPResult::ok(icsp_canister_id),
-                        Some(BurnResult::Err(burn_err)),
-                    );
-                }
-            }
-        }
-        CreateICSPResult::err(create_err) => {
-            return (CreateICSPResult::err(create_err), None);
-        }
-    }
 }
 
 /// Transform icp to cycles and top_up tp icsp
@@ -286,8 +203,9 @@ pub async fn create_icsp(
 /// ``` no_run
 /// use candid::Principal;
 /// use isp_sdk::isp::{self, TopUpArgs};
+/// use isp_sdk::IspError;
 ///
-/// pub async fn top_up_icsp() {
+/// pub async fn top_up_icsp() -> Result<(), IspError> {
 ///   println!(
 ///      "topup icsp result:{:?}\n",
 ///      isp::top_up_icsp(
@@ -297,20 +215,13 @@ pub async fn create_icsp(
 ///              icp_amount: 5_000_000 as u64,
 ///          }
 ///      )
-///           .await
+///           .await?
 ///   );
+///     Ok(())
 /// }
 /// ```
-pub async fn top_up_icsp(pem_identity_path: &str, args: TopUpArgs) -> TopUpResult {
-    let canister_id = candid::Principal::from_text(ISP_CANISTER_ID_TEXT).unwrap();
-    let response_blob = build_agent(pem_identity_path)
-        .update(&canister_id, "topUpICSP")
-        .with_arg(Encode!(&args).expect("encode error"))
-        .call_and_wait()
-        .await
-        .expect("response error");
-    let response = Decode!(&response_blob, TopUpResult).unwrap();
-    response
+pub async fn top_up_icsp(pem_identity_path: &str, args: TopUpArgs) -> Result<(), IspError> {
+    IspClient::new(pem_identity_path)?.top_up_icsp(args).await
 }
 
 #[derive(CandidType, Deserialize, Debug)]
@@ -340,9 +251,10 @@ pub enum BurnError {
 ///
 /// ``` no_run
 /// use isp_sdk::isp::{self, BurnArgs};
+/// use isp_sdk::IspError;
 /// use candid::Principal;
 ///
-/// pub async fn top_up_icsp_with_xtc() {
+/// pub async fn top_up_icsp_with_xtc() -> Result<(), IspError> {
 ///   println!(
 ///       "topup icsp with XTC result:{:?}\n",
 ///      isp::top_up_icsp_with_xtc(
@@ -352,38 +264,186 @@ pub enum BurnError {
 ///            amount: 1_000_000_000_000 as u64 - 2_000_000_000 as u64,
 ///         }
 ///     )
-///          .await
+///          .await?
 ///  );
+///     Ok(())
 /// }
 /// ```
-pub async fn top_up_icsp_with_xtc(pem_identity_path: &str, args: BurnArgs) -> BurnResult {
-    let canister_id = candid::Principal::from_text("aanaa-xaaaa-aaaah-aaeiq-cai").unwrap();
-    let response_blob = build_agent(pem_identity_path)
-        .update(&canister_id, "burn")
-        .with_arg(Encode!(&args).expect("encode error"))
-        .call_and_wait()
+pub async fn top_up_icsp_with_xtc(
+    pem_identity_path: &str,
+    args: BurnArgs,
+) -> Result<u64, IspError> {
+    IspClient::new(pem_identity_path)?
+        .top_up_icsp_with_xtc(args)
         .await
-        .expect("response error");
-    let response = Decode!(&response_blob, BurnResult).unwrap();
-    response
 }
 
-fn get_waiter() -> Delay {
-    let waiter = garcon::Delay::builder()
-        .throttle(std::time::Duration::from_millis(500))
-        .timeout(std::time::Duration::from_secs(60 * 5))
-        .build();
-    waiter
-}
+impl IspClient {
+    /// Get icsps of user, return Vec<(icsp_name, icsp_canister_id)>
+    pub async fn get_user_icsps(&self) -> Result<Vec<(String, candid::Principal)>, IspError> {
+        let response_blob = self
+            .agent
+            .query(&self.canister_ids.isp, "getUserICSPs")
+            .with_arg(Encode!()?)
+            .call()
+            .await?;
+        Ok(Decode!(&response_blob, Vec<(String, candid::Principal)>)?)
+    }
+
+    /// Get user's subAccount of the isp
+    ///
+    /// You should transfer icp to this subAccount in order to create icsp canister
+    pub async fn get_sub_account(&self) -> Result<String, IspError> {
+        let response_blob = self
+            .agent
+            .query(&self.canister_ids.isp, "getSubAccount")
+            .with_arg(Encode!()?)
+            .call()
+            .await?;
+        let response = Decode!(&response_blob, Vec<u8>)?;
+        Ok(hex::encode(response))
+    }
+
+    /// Get the icp balance of user's subAccount of the isp, the balance is e8s
+    pub async fn get_user_sub_account_icp_balance(&self) -> Result<u64, IspError> {
+        let response_blob = self
+            .agent
+            .update(&self.canister_ids.isp, "getUserSubAccountICPBalance")
+            .with_arg(Encode!()?)
+            .call_and_wait()
+            .await?;
+        Ok(Decode!(&response_blob, u64)?)
+    }
+
+    /// Transfer out icp from user's subAccount of the isp, the amount is e8s
+    ///
+    /// Return the block index of the transfer
+    pub async fn transfer_out_user_sub_account_icp(
+        &self,
+        to: &str,
+        amount: u64,
+    ) -> Result<u64, IspError> {
+        let response_blob = self
+            .agent
+            .update(&self.canister_ids.isp, "transferOutUserSubAccountICP")
+            .with_arg(Encode!(&hex::decode(to)?, &amount)?)
+            .call_and_wait()
+            .await?;
+        match Decode!(&response_blob, TransferResult)? {
+            TransferResult::Ok(block_index) => Ok(block_index),
+            TransferResult::Err(transfer_err) => Err(IspError::Transfer(transfer_err)),
+        }
+    }
+
+    /// Get admins of isp
+    pub async fn get_isp_admins(&self) -> Result<Vec<candid::Principal>, IspError> {
+        let response_blob = self
+            .agent
+            .query(&self.canister_ids.isp, "getAdmins")
+            .with_arg(Encode!()?)
+            .call()
+            .await?;
+        Ok(Decode!(&response_blob, Vec<candid::Principal>)?)
+    }
+
+    /// Get ISP's ICSP_WASM version
+    pub async fn get_isp_version(&self) -> Result<String, IspError> {
+        let response_blob = self
+            .agent
+            .query(&self.canister_ids.isp, "getVersion")
+            .with_arg(Encode!()?)
+            .call()
+            .await?;
+        Ok(Decode!(&response_blob, String)?)
+    }
+
+    /// Use icp to create a icsp canister and use the [XTC](https://github.com/Psychedelic/dank/tree/main/xtc) to top_up it
+    ///
+    /// The icp_amount is e8s, the XTC is e12s.
+    ///
+    /// Return the canister id of the new icsp. Once the icsp is created any error of the XTC top up
+    /// or of the init is returned as [`IspError::Created`], which carries the canister id.
+    pub async fn create_icsp(
+        &self,
+        icsp_name: &str,
+        icp_to_create_amount: u64,
+        xtc_to_topup_amount: u64,
+    ) -> Result<candid::Principal, IspError> {
+        // create a icsp canister
+        let response_blob = self
+            .agent
+            .update(&self.canister_ids.isp, "createICSP")
+            .with_arg(Encode!(&icsp_name, &icp_to_create_amount)?)
+            .call_and_wait()
+            .await?;
+        let icsp_canister_id = match Decode!(&response_blob, CreateICSPResult)? {
+            CreateICSPResult::ok(icsp_canister_id) => icsp_canister_id,
+            CreateICSPResult::err(create_err) => return Err(IspError::Isp(create_err)),
+        };
+        self.top_up_and_init(icsp_canister_id, xtc_to_topup_amount)
+            .await
+            .map_err(|error| IspError::Created {
+                canister_id: icsp_canister_id,
+                error: Box::new(error),
+            })?;
+        Ok(icsp_canister_id)
+    }
 
-fn build_agent(pem_identity_path: &str) -> Agent {
-    let url = "https://ic0.app".to_string();
-    let identity = Secp256k1Identity::from_pem_file(String::from(pem_identity_path)).unwrap();
-    let transport = ReqwestHttpReplicaV2Transport::create(url).expect("transport error");
-    let agent = Agent::builder()
-        .with_transport(transport)
-        .with_identity(identity)
-        .build()
-        .expect("build agent error");
-    agent
+    // Top up a new icsp with XTC and init it
+    async fn top_up_and_init(
+        &self,
+        icsp_canister_id: candid::Principal,
+        xtc_to_topup_amount: u64,
+    ) -> Result<(), IspError> {
+        // use XTC to topup icsp
+        self.top_up_icsp_with_xtc(BurnArgs {
+            canister_id: icsp_canister_id,
+            amount: xtc_to_topup_amount,
+        })
+        .await?;
+        // init icsp
+        let init_blob = self
+            .agent
+            .update(&icsp_canister_id, "init")
+            .with_arg(Encode!()?)
+            .call_and_wait()
+            .await?;
+        match Decode!(&init_blob, InitResult)? {
+            InitResult::ok(_) => Ok(()),
+            InitResult::err(data_err) => Err(IspError::Data(data_err)),
+        }
+    }
+
+    /// Transform icp to cycles and top_up tp icsp
+    pub async fn top_up_icsp(&self, args: TopUpArgs) -> Result<(), IspError> {
+        let response_blob = self
+            .agent
+            .update(&self.canister_ids.isp, "topUpICSP")
+            .with_arg(Encode!(&args)?)
+            .call_and_wait()
+            .await?;
+        match Decode!(&response_blob, TopUpResult)? {
+            TopUpResult::ok => Ok(()),
+            TopUpResult::err(top_up_err) => Err(IspError::Isp(top_up_err)),
+        }
+    }
+
+    /// Use [XTC](https://github.com/Psychedelic/dank/tree/main/xtc) to top_up icsp
+    ///
+    /// Return the block index of the burn
+    pub async fn top_up_icsp_with_xtc(&self, args: BurnArgs) -> Result<u64, IspError> {
+        let response_blob = self
+            .agent
+            .update(&self.canister_ids.xtc, "burn")
+            .with_arg(Encode!(&args)?)
+            .call_and_wait()
+            .await?;
+        match Decode!(&response_blob, BurnResult)? {
+            BurnResult::Ok(block_index) => Ok(block_index),
+            BurnResult::Err(burn_err) => Err(IspError::Burn {
+                canister_id: args.canister_id,
+                error: burn_err,
+            }),
+        }
+    }
 }
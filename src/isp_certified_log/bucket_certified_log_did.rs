@@ -1,3 +1,5 @@
+#![allow(dead_code, non_camel_case_types, non_snake_case)]
+#![allow(clippy::upper_case_acronyms)]
 use candid::Principal;
 use ic_cdk::api::call::CallResult;
 use ic_cdk::export::candid::{self, CandidType, Deserialize};
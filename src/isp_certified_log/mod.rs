@@ -1,10 +1,9 @@
 mod bucket_certified_log_did;
 mod icsp_certified_log_backend_did;
+use crate::{IspClient, IspError};
 pub use bucket_certified_log_did::CertifiedLog;
-use candid::{CandidType, Decode, Encode, Nat};
-use garcon::Delay;
-use ic_agent::agent::http_transport::ReqwestHttpReplicaV2Transport;
-use ic_agent::{identity::Secp256k1Identity, Agent};
+use candid::{Decode, Encode};
+use ic_agent::Agent;
 pub use icsp_certified_log_backend_did::{Buckets, StoreLog};
 
 /// Get buckets of user's icsp_certified_log
@@ -12,12 +11,12 @@ pub use icsp_certified_log_backend_did::{Buckets, StoreLog};
 /// # Examples
 ///
 /// ```no_run
-/// use isp_sdk::isp_certified_log;
+/// use isp_sdk::{isp_certified_log, IspError};
 ///
-/// pub async fn get_buckets() {
+/// pub async fn get_buckets() -> Result<(), IspError> {
 ///     let response =
 ///         isp_certified_log::get_buckets("identities/identity.pem", "4radi-oqaaa-aaaan-qapwa-cai")
-///             .await;
+///             .await?;
 ///     match response {
 ///         Some(response) => {
 ///             println!("old buckets:");
@@ -32,21 +31,16 @@ pub use icsp_certified_log_backend_did::{Buckets, StoreLog};
 ///         }
 ///         None => println!("icsp do not have buckets"),
 ///     }
+///     Ok(())
 /// }
 /// ```
 pub async fn get_buckets(
     pem_identity_path: &str,
     icsp_log_canister_id_text: &str,
-) -> Option<Buckets> {
-    let canister_id = candid::Principal::from_text(icsp_log_canister_id_text).unwrap();
-    let response_blob = build_agent(pem_identity_path)
-        .query(&canister_id, "getBuckets")
-        .with_arg(Encode!().expect("encode piece failed"))
-        .call()
+) -> Result<Option<Buckets>, IspError> {
+    build_handle(pem_identity_path, icsp_log_canister_id_text)?
+        .get_buckets()
         .await
-        .expect("response error");
-    let response = Decode!(&response_blob, Option<Buckets>).unwrap();
-    response
 }
 
 /// Get the number of certified logs
@@ -54,26 +48,24 @@ pub async fn get_buckets(
 /// # Examples
 ///
 /// ```no_run
-/// use isp_sdk::isp_certified_log;
+/// use isp_sdk::{isp_certified_log, IspError};
 ///
-/// pub async fn get_log_num() {
+/// pub async fn get_log_num() -> Result<(), IspError> {
 ///     println!(
 ///         "log num:{:?}",
 ///         isp_certified_log::get_log_num("identities/identity.pem", "4radi-oqaaa-aaaan-qapwa-cai")
-///             .await
+///             .await?
 ///     );
+///     Ok(())
 /// }
 /// ```
-pub async fn get_log_num(pem_identity_path: &str, icsp_log_canister_id_text: &str) -> u128 {
-    let canister_id = candid::Principal::from_text(icsp_log_canister_id_text).unwrap();
-    let response_blob = build_agent(pem_identity_path)
-        .query(&canister_id, "getLogNum")
-        .with_arg(Encode!().expect("encode piece failed"))
-        .call()
+pub async fn get_log_num(
+    pem_identity_path: &str,
+    icsp_log_canister_id_text: &str,
+) -> Result<u128, IspError> {
+    build_handle(pem_identity_path, icsp_log_canister_id_text)?
+        .get_log_num()
         .await
-        .expect("response error");
-    let response = Decode!(&response_blob, u128).unwrap();
-    response
 }
 
 /// Get logs from start to end (0 ... n-1)
@@ -81,22 +73,23 @@ pub async fn get_log_num(pem_identity_path: &str, icsp_log_canister_id_text: &st
 /// # Examples
 ///
 /// ```no_run
-/// use isp_sdk::isp_certified_log;
+/// use isp_sdk::{isp_certified_log, IspError};
 ///
-/// pub async fn get_logs() {
+/// pub async fn get_logs() -> Result<(), IspError> {
 ///     let response = isp_certified_log::get_logs(
 ///         "identities/identity.pem",
 ///         "4radi-oqaaa-aaaan-qapwa-cai",
 ///         0,
 ///         isp_certified_log::get_log_num("identities/identity.pem", "4radi-oqaaa-aaaan-qapwa-cai")
-///             .await
+///             .await?
 ///             - 1,
 ///     )
-///         .await;
+///         .await?;
 ///     match response {
 ///         Some(response) => println!("{:?}", response),
 ///         None => println!("no logs"),
 ///     }
+///     Ok(())
 /// }
 /// ```
 pub async fn get_logs(
@@ -104,45 +97,10 @@ pub async fn get_logs(
     icsp_log_canister_id_text: &str,
     start: u128,
     end: u128,
-) -> Option<Vec<CertifiedLog>> {
-    let canister_id = candid::Principal::from_text(icsp_log_canister_id_text).unwrap();
-    let agent = build_agent(pem_identity_path);
-    let response_blob = agent
-        .query(&canister_id, "getLogs")
-        .with_arg(Encode!(&start, &end).expect("encode piece failed"))
-        .call()
+) -> Result<Option<Vec<CertifiedLog>>, IspError> {
+    build_handle(pem_identity_path, icsp_log_canister_id_text)?
+        .get_logs(start, end)
         .await
-        .expect("response error");
-    let response = Decode!(&response_blob, Option<Vec<(u64, u64, candid::Principal)>>).unwrap();
-    match response {
-        Some(ans) => {
-            let mut payload: Vec<CertifiedLog> = Vec::new();
-            for i in &ans {
-                let response_blob = agent
-                    .query(
-                        &candid::Principal::from_text(i.2.to_text()).unwrap(),
-                        "getLogs",
-                    )
-                    .with_arg(Encode!(&i.0, &i.1).expect("encode piece failed"))
-                    .call()
-                    .await
-                    .expect("response error");
-                let response = Decode!(&response_blob, Option<Vec<CertifiedLog>>).unwrap();
-                match response {
-                    Some(mut log) => {
-                        payload.append(&mut log);
-                    }
-                    None => {}
-                }
-            }
-            if payload.is_empty() {
-                return None;
-            } else {
-                return Some(payload);
-            }
-        }
-        None => return None,
-    }
 }
 
 /// Get icsp_certified_log admins
@@ -150,31 +108,26 @@ pub async fn get_logs(
 /// # Examples
 ///
 /// ```no_run
-/// use isp_sdk::isp_certified_log;
+/// use isp_sdk::{isp_certified_log, IspError};
 ///
-/// pub async fn get_admins() {
+/// pub async fn get_admins() -> Result<(), IspError> {
 ///     println!("admins");
 ///     for i in
 ///     &isp_certified_log::get_admins("identities/identity.pem", "4radi-oqaaa-aaaan-qapwa-cai")
-///         .await
+///         .await?
 ///     {
 ///         println!("{:?}", i.to_text());
 ///     }
+///     Ok(())
 /// }
 /// ```
 pub async fn get_admins(
     pem_identity_path: &str,
     icsp_log_canister_id_text: &str,
-) -> Vec<candid::Principal> {
-    let canister_id = candid::Principal::from_text(icsp_log_canister_id_text).unwrap();
-    let response_blob = build_agent(pem_identity_path)
-        .query(&canister_id, "getAdmins")
-        .with_arg(Encode!().expect("encode piece failed"))
-        .call()
+) -> Result<Vec<candid::Principal>, IspError> {
+    build_handle(pem_identity_path, icsp_log_canister_id_text)?
+        .get_admins()
         .await
-        .expect("response error");
-    let response = Decode!(&response_blob, Vec<candid::Principal>).unwrap();
-    response
 }
 
 /// Store a certified log
@@ -183,8 +136,9 @@ pub async fn get_admins(
 ///
 /// ```no_run
 /// use isp_sdk::isp_certified_log::{self, StoreLog};
+/// use isp_sdk::IspError;
 ///
-/// pub async fn store() {
+/// pub async fn store() -> Result<(), IspError> {
 ///     isp_certified_log::store(
 ///         "identities/identity.pem",
 ///         "4radi-oqaaa-aaaan-qapwa-cai",
@@ -192,19 +146,18 @@ pub async fn get_admins(
 ///             context: "test".to_string(),
 ///         },
 ///     )
-///         .await;
+///         .await?;
+///     Ok(())
 /// }
 /// ```
-pub async fn store(pem_identity_path: &str, icsp_log_canister_id_text: &str, args: StoreLog) {
-    let canister_id = candid::Principal::from_text(icsp_log_canister_id_text).unwrap();
-    let response_blob = build_agent(pem_identity_path)
-        .update(&canister_id, "store")
-        .with_arg(Encode!(&args).expect("encode piece failed"))
-        .call_and_wait()
+pub async fn store(
+    pem_identity_path: &str,
+    icsp_log_canister_id_text: &str,
+    args: StoreLog,
+) -> Result<(), IspError> {
+    build_handle(pem_identity_path, icsp_log_canister_id_text)?
+        .store(args)
         .await
-        .expect("response error");
-    let response = Decode!(&response_blob, ()).unwrap();
-    response
 }
 
 // pub async fn update_bucket_canister_controller(
@@ -213,14 +166,14 @@ pub async fn store(pem_identity_path: &str, icsp_log_canister_id_text: &str, arg
 //     bucket_canister_id: candid::Principal,
 //     contoller: Vec<candid::Principal>,
 // ) -> bool {
-//     let canister_id = candid::Principal::from_text(icsp_log_canister_id_text).unwrap();
+//     let canister_id = candid::Principal::from_text(icsp_log_canister_id_text)?;
 //     let response_blob = build_agent(pem_identity_path)
 //         .update(&canister_id, "updateBucketCanisterController")
-//         .with_arg(Encode!(&bucket_canister_id, &contoller).expect("encode piece failed"))
+//         .with_arg(Encode!(&bucket_canister_id, &contoller)?)
 //         .call_and_wait(get_waiter())
 //         .await
-//         .expect("response error");
-//     let response = Decode!(&response_blob, bool).unwrap();
+//         ?;
+//     let response = Decode!(&response_blob, bool)?;
 //     response
 // }
 
@@ -229,32 +182,27 @@ pub async fn store(pem_identity_path: &str, icsp_log_canister_id_text: &str, arg
 /// # Examples
 ///
 /// ```no_run
-/// use isp_sdk::isp_certified_log;
+/// use isp_sdk::{isp_certified_log, IspError};
 ///
-/// pub async fn add_admin() {
+/// pub async fn add_admin() -> Result<(), IspError> {
 ///     isp_certified_log::add_admin(
 ///         "identities/identity.pem",
 ///         "4radi-oqaaa-aaaan-qapwa-cai",
 ///         "bxgws-37y5d-tgmpr-hekbp-y3uxo-yicgs-fo7p3-ccnta-kidrz-74onh-pae",
 ///     )
-///         .await;
+///         .await?;
+///     Ok(())
 /// }
 /// ```
 pub async fn add_admin(
     pem_identity_path: &str,
     icsp_log_canister_id_text: &str,
     new_admin_text: &str,
-) {
-    let canister_id = candid::Principal::from_text(icsp_log_canister_id_text).unwrap();
-    let new_admin = candid::Principal::from_text(new_admin_text).unwrap();
-    let response_blob = build_agent(pem_identity_path)
-        .update(&canister_id, "addAdmin")
-        .with_arg(Encode!(&new_admin).expect("encode piece failed"))
-        .call_and_wait()
+) -> Result<(), IspError> {
+    let new_admin = candid::Principal::from_text(new_admin_text)?;
+    build_handle(pem_identity_path, icsp_log_canister_id_text)?
+        .add_admin(new_admin)
         .await
-        .expect("response error");
-    let response = Decode!(&response_blob, ()).unwrap();
-    response
 }
 
 /// Delete a icsp_certified_log admin
@@ -262,50 +210,154 @@ pub async fn add_admin(
 /// # Examples
 ///
 /// ```no_run
-/// use isp_sdk::isp_certified_log;
+/// use isp_sdk::{isp_certified_log, IspError};
 ///
-/// pub async fn delete_admin() {
+/// pub async fn delete_admin() -> Result<(), IspError> {
 ///     isp_certified_log::delete_admin(
 ///         "identities/identity.pem",
 ///         "4radi-oqaaa-aaaan-qapwa-cai",
 ///         "bxgws-37y5d-tgmpr-hekbp-y3uxo-yicgs-fo7p3-ccnta-kidrz-74onh-pae",
 ///     )
-///         .await;
+///         .await?;
+///     Ok(())
 /// }
 /// ```
 pub async fn delete_admin(
     pem_identity_path: &str,
     icsp_log_canister_id_text: &str,
     old_admin_text: &str,
-) {
-    let canister_id = candid::Principal::from_text(icsp_log_canister_id_text).unwrap();
-    let old_admin = candid::Principal::from_text(old_admin_text).unwrap();
-    let response_blob = build_agent(pem_identity_path)
-        .update(&canister_id, "deleteAdmin")
-        .with_arg(Encode!(&old_admin).expect("encode piece failed"))
-        .call_and_wait()
+) -> Result<(), IspError> {
+    let old_admin = candid::Principal::from_text(old_admin_text)?;
+    build_handle(pem_identity_path, icsp_log_canister_id_text)?
+        .delete_admin(old_admin)
         .await
-        .expect("response error");
-    let response = Decode!(&response_blob, ()).unwrap();
-    response
 }
 
-fn get_waiter() -> Delay {
-    let waiter = garcon::Delay::builder()
-        .throttle(std::time::Duration::from_millis(500))
-        .timeout(std::time::Duration::from_secs(60 * 5))
-        .build();
-    waiter
+/// A handle bound to one icsp_certified_log canister, created by [`IspClient::certified_log`]
+///
+/// Every call reuses the agent of the client that created the handle.
+#[derive(Clone)]
+pub struct CertifiedLogHandle {
+    agent: Agent,
+    canister_id: candid::Principal,
 }
 
-fn build_agent(pem_identity_path: &str) -> Agent {
-    let url = "https://ic0.app".to_string();
-    let identity = Secp256k1Identity::from_pem_file(String::from(pem_identity_path)).unwrap();
-    let transport = ReqwestHttpReplicaV2Transport::create(url).expect("transport error");
-    let agent = Agent::builder()
-        .with_transport(transport)
-        .with_identity(identity)
-        .build()
-        .expect("build agent error");
-    agent
+impl CertifiedLogHandle {
+    pub(crate) fn new(agent: Agent, canister_id: candid::Principal) -> CertifiedLogHandle {
+        CertifiedLogHandle { agent, canister_id }
+    }
+
+    /// The icsp_certified_log canister this handle is bound to
+    pub fn canister_id(&self) -> candid::Principal {
+        self.canister_id
+    }
+
+    /// Get buckets of user's icsp_certified_log
+    pub async fn get_buckets(&self) -> Result<Option<Buckets>, IspError> {
+        let response_blob = self
+            .agent
+            .query(&self.canister_id, "getBuckets")
+            .with_arg(Encode!()?)
+            .call()
+            .await?;
+        Ok(Decode!(&response_blob, Option<Buckets>)?)
+    }
+
+    /// Get the number of certified logs
+    pub async fn get_log_num(&self) -> Result<u128, IspError> {
+        let response_blob = self
+            .agent
+            .query(&self.canister_id, "getLogNum")
+            .with_arg(Encode!()?)
+            .call()
+            .await?;
+        Ok(Decode!(&response_blob, u128)?)
+    }
+
+    /// Get logs from start to end (0 ... n-1)
+    pub async fn get_logs(
+        &self,
+        start: u128,
+        end: u128,
+    ) -> Result<Option<Vec<CertifiedLog>>, IspError> {
+        let response_blob = self
+            .agent
+            .query(&self.canister_id, "getLogs")
+            .with_arg(Encode!(&start, &end)?)
+            .call()
+            .await?;
+        let ans = match Decode!(&response_blob, Option<Vec<(u64, u64, candid::Principal)>>)? {
+            Some(ans) => ans,
+            None => return Ok(None),
+        };
+        let mut payload: Vec<CertifiedLog> = Vec::new();
+        for i in &ans {
+            let response_blob = self
+                .agent
+                .query(&i.2, "getLogs")
+                .with_arg(Encode!(&i.0, &i.1)?)
+                .call()
+                .await?;
+            if let Some(mut log) = Decode!(&response_blob, Option<Vec<CertifiedLog>>)? {
+                payload.append(&mut log);
+            }
+        }
+        if payload.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(payload))
+        }
+    }
+
+    /// Get icsp_certified_log admins
+    pub async fn get_admins(&self) -> Result<Vec<candid::Principal>, IspError> {
+        let response_blob = self
+            .agent
+            .query(&self.canister_id, "getAdmins")
+            .with_arg(Encode!()?)
+            .call()
+            .await?;
+        Ok(Decode!(&response_blob, Vec<candid::Principal>)?)
+    }
+
+    /// Store a certified log
+    pub async fn store(&self, args: StoreLog) -> Result<(), IspError> {
+        let response_blob = self
+            .agent
+            .update(&self.canister_id, "store")
+            .with_arg(Encode!(&args)?)
+            .call_and_wait()
+            .await?;
+        Ok(Decode!(&response_blob, ())?)
+    }
+
+    /// Add a icsp_certified_log admin
+    pub async fn add_admin(&self, new_admin: candid::Principal) -> Result<(), IspError> {
+        let response_blob = self
+            .agent
+            .update(&self.canister_id, "addAdmin")
+            .with_arg(Encode!(&new_admin)?)
+            .call_and_wait()
+            .await?;
+        Ok(Decode!(&response_blob, ())?)
+    }
+
+    /// Delete a icsp_certified_log admin
+    pub async fn delete_admin(&self, old_admin: candid::Principal) -> Result<(), IspError> {
+        let response_blob = self
+            .agent
+            .update(&self.canister_id, "deleteAdmin")
+            .with_arg(Encode!(&old_admin)?)
+            .call_and_wait()
+            .await?;
+        Ok(Decode!(&response_blob, ())?)
+    }
+}
+
+fn build_handle(
+    pem_identity_path: &str,
+    icsp_log_canister_id_text: &str,
+) -> Result<CertifiedLogHandle, IspError> {
+    let canister_id = candid::Principal::from_text(icsp_log_canister_id_text)?;
+    Ok(IspClient::new(pem_identity_path)?.certified_log(canister_id))
 }
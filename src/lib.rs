@@ -9,9 +9,27 @@
 //! 4. call ``isp_sdk::icsp::store_file`` to store the file
 //! 5. Call other interfaces to complete related operations
 //!
+//! Every free function builds a new agent from the pem file. Services that make many calls
+//! should create one [`IspClient`] and reuse it, ICSP and icsp_certified_log operations are
+//! available on the handles returned by [`IspClient::icsp`] and [`IspClient::certified_log`],
+//! a single bucket canister can be called through [`IspClient::bucket`].
+//! Use [`IspClient::builder`] with a [`Network`] to talk to a local dfx replica or a custom url,
+//! and with any [`ic_agent::Identity`] such as a dfx Ed25519 key, an in-memory pem or anonymous.
+//!
 //! ## Architecture
 //! ![](https://scf3f-cyaaa-aaaal-aas3q-cai.raw.ic0.app/fk/VOhoOCto-8SRSfYZ1jKhE)
 //!
+pub mod bucket;
+mod client;
+mod error;
 pub mod icsp;
 pub mod isp;
 pub mod isp_certified_log;
+mod network;
+
+pub use bucket::BucketHandle;
+pub use client::{IspClient, IspClientBuilder};
+pub use error::{ChunkFailure, IspError};
+pub use icsp::IcspHandle;
+pub use isp_certified_log::CertifiedLogHandle;
+pub use network::{CanisterIds, Network};
@@ -0,0 +1,92 @@
+/// The IC network a client talks to
+///
+/// The root key of every network except [`Network::Mainnet`] is fetched from the replica when
+/// the client is built, never use a non-mainnet network to talk to the real IC.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum Network {
+    /// The IC mainnet at `https://ic0.app`
+    #[default]
+    Mainnet,
+    /// A local dfx replica at `http://127.0.0.1:4943`
+    Local,
+    /// A replica or boundary node at a custom url, e.g. `http://127.0.0.1:8000`
+    Custom(String),
+}
+
+impl Network {
+    /// The url of the replica
+    pub fn url(&self) -> &str {
+        match self {
+            Network::Mainnet => "https://ic0.app",
+            Network::Local => "http://127.0.0.1:4943",
+            Network::Custom(url) => url,
+        }
+    }
+
+    /// Whether the root key has to be fetched from the replica
+    pub fn needs_root_key(&self) -> bool {
+        *self != Network::Mainnet
+    }
+}
+
+/// Canister ids of the ISP deployment and the canisters it depends on
+///
+/// Only the mainnet deployment is known, a client for any other network uses the mainnet ids
+/// unless they are overridden with [`IspClientBuilder::with_canister_ids`](crate::IspClientBuilder::with_canister_ids).
+///
+/// # Examples
+///
+/// ``` no_run
+/// use candid::Principal;
+/// use isp_sdk::{CanisterIds, IspClient, IspError, Network};
+///
+/// pub async fn staging_client() -> Result<IspClient, IspError> {
+///     IspClient::builder()
+///         .with_network(Network::Custom("https://staging.example.com".to_string()))
+///         .with_canister_ids(CanisterIds {
+///             isp: Principal::from_text("rrkah-fqaaa-aaaaa-aaaaq-cai")?,
+///             ..CanisterIds::mainnet()
+///         })
+///         .with_pem_file("identities/identity.pem")
+///         .build()
+///         .await
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CanisterIds {
+    /// The isp canister which creates and tops up icsp canisters
+    pub isp: candid::Principal,
+    /// The [XTC](https://github.com/Psychedelic/dank/tree/main/xtc) canister used to top up icsp canisters
+    pub xtc: candid::Principal,
+    /// The icp ledger canister
+    pub ledger: candid::Principal,
+    /// The cycles minting canister
+    pub cmc: candid::Principal,
+}
+
+impl CanisterIds {
+    /// Canister ids of the mainnet deployment
+    pub fn mainnet() -> CanisterIds {
+        CanisterIds {
+            isp: principal(MAINNET_ISP_CANISTER_ID_TEXT),
+            xtc: principal(MAINNET_XTC_CANISTER_ID_TEXT),
+            ledger: principal(MAINNET_LEDGER_CANISTER_ID_TEXT),
+            cmc: principal(MAINNET_CMC_CANISTER_ID_TEXT),
+        }
+    }
+}
+
+impl Default for CanisterIds {
+    fn default() -> Self {
+        CanisterIds::mainnet()
+    }
+}
+
+static MAINNET_ISP_CANISTER_ID_TEXT: &str = "p2pki-xyaaa-aaaan-qatua-cai";
+static MAINNET_XTC_CANISTER_ID_TEXT: &str = "aanaa-xaaaa-aaaah-aaeiq-cai";
+static MAINNET_LEDGER_CANISTER_ID_TEXT: &str = "ryjl3-tyaaa-aaaaa-aaaba-cai";
+static MAINNET_CMC_CANISTER_ID_TEXT: &str = "rkp4c-7iaaa-aaaaa-aaaca-cai";
+
+fn principal(canister_id_text: &str) -> candid::Principal {
+    candid::Principal::from_text(canister_id_text).expect("invalid builtin canister id")
+}